@@ -0,0 +1,3 @@
+import { missing } from "./missing.mjs";
+
+console.log(missing);
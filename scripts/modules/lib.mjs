@@ -0,0 +1,7 @@
+globalThis.libLoads = (globalThis.libLoads ?? 0) + 1;
+
+export const name = "lib";
+
+export function loads() {
+  return globalThis.libLoads;
+}
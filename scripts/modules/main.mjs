@@ -0,0 +1,13 @@
+import { name } from "./lib.mjs";
+import { greet } from "./util/greet.mjs";
+
+console.log("Loaded module", name);
+
+// Top-level await must settle before the module is considered loaded
+await new Promise((resolve) => setTimeout(resolve, 10));
+
+globalThis.moduleResult = greet();
+
+addEventListener("fetch", (event) => {
+  event.respondWith(new Response(globalThis.moduleResult));
+});
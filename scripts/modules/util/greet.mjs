@@ -0,0 +1,5 @@
+import { name, loads } from "../lib.mjs";
+
+export function greet() {
+  return `Hello from ${name} (loaded ${loads()} times)`;
+}
@@ -1,11 +1,20 @@
 use tokio::time::Duration;
 
+use lib::core::EvalError;
 use lib::core::JsRuntime;
 use lib::core::RuntimeBasicMessage;
 use lib::fetch::JsRequest;
 use lib::fetch::RuntimeFetchMessage;
 use lib::utils::file::read_script_file;
 
+/// Print the error and exit, the worker cannot go on after a failed evaluation
+fn unwrap_or_exit<T>(result: Result<T, EvalError>) -> T {
+    result.unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    })
+}
+
 async fn run(args: Vec<String>) {
     let snapshot = match std::fs::read("snapshot.bin") {
         Ok(snapshot) => Some(snapshot),
@@ -20,18 +29,21 @@ async fn run(args: Vec<String>) {
     match args.get(1) {
         Some(arg) if arg == "eval" => match args.get(2) {
             Some(script) => {
-                println!("{}", rt.eval(script).unwrap());
-                rt.run_event_loop().await;
+                println!("{}", unwrap_or_exit(rt.eval(script)));
+                unwrap_or_exit(rt.run_event_loop().await);
             }
             None => {
                 eprintln!("Usage: {} eval <code>", args[0]);
                 std::process::exit(1);
             }
         },
+        Some(path) if path.ends_with(".mjs") => {
+            unwrap_or_exit(rt.load_main_module(path).await);
+        }
         Some(path) => {
             let script = &read_script_file(path);
-            rt.eval(script).unwrap();
-            rt.run_event_loop().await;
+            unwrap_or_exit(rt.execute_script(path, script));
+            unwrap_or_exit(rt.run_event_loop().await);
         }
         None => {
             eprintln!("Usage: {} <file> or {} eval <code>", args[0], args[0]);
@@ -50,22 +62,30 @@ async fn run(args: Vec<String>) {
 
             match event_type.as_str() {
                 "fetch" => {
-                    let mut event = RuntimeFetchMessage::new(JsRequest {
-                        url: "https://example.org/get".to_string(),
-                        method: "GET".to_string(),
-                        // headers: vec![],
-                        // body: None,
-                    });
+                    let mut event = RuntimeFetchMessage::new(JsRequest::new(
+                        "https://example.org/get".to_string(),
+                        "GET".to_string(),
+                    ));
 
-                    rt.send_message(&mut event);
+                    if let Err(err) = rt.send_message(&mut event) {
+                        eprintln!("{}", err);
+                    }
 
-                    let time = std::time::SystemTime::now();
-                    rt.run_event_loop().await;
-                    println!("Time EvL: {:?}", time.elapsed().unwrap());
+                    let response = async {
+                        let mut res = event.get_response().await?;
 
+                        if let Err(err) = res.collect_body().await {
+                            eprintln!("Response stream failed: {}", err);
+                        }
+
+                        Some(res)
+                    };
+
+                    // Streamed bodies are produced while the event loop runs
                     let time = std::time::SystemTime::now();
-                    let res = event.get_response().await;
-                    println!("Time RES: {:?}", time.elapsed().unwrap());
+                    let (result, res) = tokio::join!(rt.run_event_loop(), response);
+                    unwrap_or_exit(result);
+                    println!("Time EvL: {:?}", time.elapsed().unwrap());
 
                     match res {
                         Some(res) => {
@@ -79,14 +99,20 @@ async fn run(args: Vec<String>) {
                 _ => {
                     let mut event = RuntimeBasicMessage::new(event_type);
 
-                    rt.send_message(&mut event);
+                    if let Err(err) = rt.send_message(&mut event) {
+                        eprintln!("{}", err);
+                    }
 
-                    rt.run_event_loop().await;
+                    unwrap_or_exit(rt.run_event_loop().await);
                 }
             };
         }
     }
 
+    for report in rt.take_error_reports() {
+        eprintln!("{}", report);
+    }
+
     println!("Time: {:?}", start.elapsed().unwrap());
 }
 
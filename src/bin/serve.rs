@@ -1,65 +1,417 @@
+use lib::core::EvalError;
+use lib::core::HeapLimits;
+use lib::core::JsError;
 use lib::core::JsRuntime;
+use lib::core::JsRuntimeBuilder;
+use lib::fetch::actix::origin_request;
+use lib::fetch::HttpClient;
+use lib::fetch::JsRequest;
+use lib::fetch::JsResponse;
+use lib::fetch::NoResponse;
+use lib::fetch::ReqwestClient;
 use lib::fetch::RuntimeFetchMessage;
+use lib::pool::PoolOptions;
+use lib::pool::RuntimePool;
 use lib::utils::file::read_script_file;
 
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use std::sync::Mutex;
 use std::time::Duration;
 
 use actix_web::web;
+use actix_web::web::Bytes;
 use actix_web::web::Data;
 use actix_web::App;
 use actix_web::HttpRequest;
 use actix_web::HttpResponse;
 use actix_web::HttpServer;
 
-struct AppState {
-    rt: Arc<Mutex<JsRuntime>>,
+use tokio::sync::oneshot;
+
+/// Everything needed to (re)create the runtime of a worker
+struct WorkerConfig {
+    path: String,
+    script: String,
+    snapshot: Option<Vec<u8>>,
+    heap_limits: Option<HeapLimits>,
+    time_limit: Duration,
+    /// How long the worker may take to respond
+    response_timeout: Duration,
+    /// How long work left after the response (waitUntil promises, streamed
+    /// bodies) may keep running
+    grace_period: Duration,
+    jitless: bool,
 }
 
-async fn handle_request(data: Data<AppState>, req: HttpRequest) -> HttpResponse {
-    let worker_id = format!("{}", actix_web::rt::System::current().id());
-    let mut ctx = data.rt.lock().unwrap();
+impl WorkerConfig {
+    async fn create_runtime(&self) -> JsRuntime {
+        let mut builder = JsRuntimeBuilder::new().time_limit(self.time_limit);
 
-    println!("Worker {} will emit fetch event", worker_id);
+        if let Some(snapshot) = &self.snapshot {
+            builder = builder.snapshot(snapshot.clone());
+        }
 
-    let mut fetch = RuntimeFetchMessage::new(req.into());
+        if let Some(heap_limits) = self.heap_limits {
+            builder = builder.heap_limits(heap_limits);
+        }
 
-    match ctx.send_message(&mut fetch) {
-        Some(_) => {}
-        None => {
-            return HttpResponse::InternalServerError()
-                .content_type("text/html; charset=utf-8")
-                .body("Cannot create event, check your response type");
+        if self.jitless {
+            builder = builder.v8_flag("--jitless");
         }
-    };
 
-    println!("Worker {} waiting for resp", worker_id);
+        let mut rt = match builder.build() {
+            Ok(rt) => rt,
+            Err(err) => {
+                eprintln!("Cannot create runtime: {}", err);
+                std::process::exit(1);
+            }
+        };
+
+        let result = match self.path.ends_with(".mjs") {
+            true => rt.load_main_module(&self.path).await,
+            false => rt.execute_script(&self.path, &self.script).map(|_| ()),
+        };
+
+        if let Err(err) = result {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+
+        rt
+    }
+}
+
+/// Origin receiving the requests the worker did not respond to
+struct Origin {
+    url: String,
+    client: ReqwestClient,
+    /// How long the origin may take to respond
+    timeout: Duration,
+}
+
+/// How the worker handled a fetch event
+enum FetchOutcome {
+    Response(JsResponse),
+    NoResponse(NoResponse),
+}
 
-    // Poll timers
+type ResponseSender = oneshot::Sender<(usize, Result<FetchOutcome, EvalError>)>;
+
+/// Fetch event to be handled by a pooled runtime
+struct FetchJob {
+    request: JsRequest,
+    request_id: String,
+    sender: ResponseSender,
+}
+
+/// Emit the fetch event, the outcome is sent as soon as the worker responds
+/// or gives up while the event loop keeps running (e.g. to stream the body)
+/// until the event settles, for at most the grace period
+async fn run_fetch(
+    rt: &mut JsRuntime,
+    config: &WorkerConfig,
+    worker_id: usize,
+    request: JsRequest,
+    sender: &mut Option<ResponseSender>,
+) -> Result<(), EvalError> {
+    let mut fetch = RuntimeFetchMessage::new(request);
+
+    rt.send_message(&mut fetch)?;
+
+    // Poll timers and ops
     let time = std::time::SystemTime::now();
-    ctx.run_event_loop().await;
+    let mut disconnected = false;
+
+    // Handlers ignoring the abort of their request are given up on as well
+    let deadline = tokio::time::sleep(config.response_timeout);
+    tokio::pin!(deadline);
+
+    // Whether the worker responded or gave up before its event loop ended
+    let decided = loop {
+        {
+            let event_loop = rt.run_event_loop();
+            tokio::pin!(event_loop);
+
+            // The receiver is dropped when actix notices the client is gone
+            let client_gone = async {
+                match sender.as_mut() {
+                    Some(sender) if !disconnected => sender.closed().await,
+                    _ => std::future::pending().await,
+                }
+            };
+
+            tokio::select! {
+                biased;
+
+                response = fetch.get_response() => break Some(response),
+                _ = client_gone => disconnected = true,
+                result = &mut event_loop => {
+                    result?;
+                    break None;
+                }
+                _ = &mut deadline => {
+                    eprintln!("Worker {} did not respond in time", worker_id);
+
+                    if let Some(sender) = sender.take() {
+                        let outcome = FetchOutcome::NoResponse(NoResponse::TimedOut);
+                        sender.send((worker_id, Ok(outcome))).ok();
+                    }
+
+                    break None;
+                }
+            }
+        }
+
+        // The event loop is resumed once the worker knows
+        println!("Worker {} lost its client, aborting request", worker_id);
+        rt.send_message(&mut RuntimeFetchMessage::abort_message())?;
+    };
+
+    if let Some(response) = decided {
+        send_outcome(rt, worker_id, &mut fetch, response, sender);
+
+        // Left over work of the request, other timers and ops of the worker
+        // may keep the event loop running
+        let settled = async {
+            tokio::select! {
+                biased;
+
+                result = rt.run_event_loop() => result,
+                _ = fetch.settled() => Ok(()),
+            }
+        };
+
+        match tokio::time::timeout(config.grace_period, settled).await {
+            Ok(result) => result?,
+            Err(_) => {
+                eprintln!(
+                    "Worker {} exceeded its grace period, dropping pending work",
+                    worker_id
+                );
+
+                // The client sees the body fail and the worker its request
+                // abort, rather than both waiting for work that never runs
+                rt.abort_response_streams("Grace period exceeded");
+                rt.send_message(&mut RuntimeFetchMessage::abort_message())?;
+            }
+        }
+    }
+
+    // Left over work of the request must not run during the next one, its
+    // bodies cannot be completed anymore
+    rt.cancel_pending();
+    rt.abort_response_streams("Response stream was not closed");
+
     println!("Time EvL: {:?}", time.elapsed().unwrap());
 
-    let timeout = tokio::time::timeout(Duration::from_millis(1000), fetch.get_response());
+    send_outcome(rt, worker_id, &mut fetch, None, sender);
 
-    // Did we timeout or did we receive a response?
-    let time = std::time::SystemTime::now();
-    let response = match timeout.await {
-        Ok(rcv) => rcv,
-        Err(_) => {
+    Ok(())
+}
+
+/// Send how the worker handled the event, unless it was already sent.
+/// Errors escaping the worker fail the request, unless it responded
+fn send_outcome(
+    rt: &mut JsRuntime,
+    worker_id: usize,
+    fetch: &mut RuntimeFetchMessage,
+    response: Option<JsResponse>,
+    sender: &mut Option<ResponseSender>,
+) {
+    let reports = rt.take_error_reports();
+
+    for report in reports.iter() {
+        eprintln!("{}", report);
+    }
+
+    let sender = match sender.take() {
+        Some(sender) => sender,
+        None => return,
+    };
+
+    let response = response.or_else(|| fetch.try_get_response());
+    let no_response = fetch.no_response();
+
+    // Failures passed through are already reported
+    let outcome = match (response, no_response, reports.into_iter().next()) {
+        (Some(response), _, _) => Ok(FetchOutcome::Response(response)),
+        (None, Some(NoResponse::PassThrough), _) => {
+            Ok(FetchOutcome::NoResponse(NoResponse::PassThrough))
+        }
+        (None, _, Some(report)) => Err(EvalError::RuntimeError(report.error)),
+        (None, Some(reason), None) => Ok(FetchOutcome::NoResponse(reason)),
+        (None, None, None) => Err(EvalError::RuntimeError(JsError {
+            message: String::from("Cannot get response"),
+            ..Default::default()
+        })),
+    };
+
+    // Client may have gone away
+    sender.send((worker_id, outcome)).ok();
+}
+
+/// Runs on the pool thread owning `rt`, the runtime is recreated if its heap is exhausted
+async fn handle_fetch(
+    config: &WorkerConfig,
+    worker_id: usize,
+    mut rt: JsRuntime,
+    job: FetchJob,
+) -> JsRuntime {
+    println!("Worker {} will emit fetch event", worker_id);
+
+    let mut sender = Some(job.sender);
+
+    rt.set_worker_id(Some(worker_id.to_string()));
+    rt.set_request_id(Some(job.request_id));
+    let result = run_fetch(&mut rt, config, worker_id, job.request, &mut sender).await;
+    rt.set_request_id(None);
+
+    // Reported after the request failed for another reason
+    for report in rt.take_error_reports() {
+        eprintln!("{}", report);
+    }
+
+    let err = match result {
+        Ok(()) => return rt,
+        Err(err) => err,
+    };
+
+    if err == EvalError::HeapLimitExceeded {
+        println!(
+            "Worker {} exhausted its heap, recreating runtime",
+            worker_id
+        );
+        // Isolates must be dropped in reverse creation order
+        drop(rt);
+        rt = config.create_runtime().await;
+    }
+
+    match sender {
+        // Client may have gone away
+        Some(sender) => {
+            sender.send((worker_id, Err(err))).ok();
+        }
+        None => eprintln!("Worker {} failed after responding: {}", worker_id, err),
+    }
+
+    rt
+}
+
+/// Answer for a failed event
+fn error_response(worker_id: usize, err: EvalError) -> HttpResponse {
+    match err {
+        EvalError::HeapLimitExceeded => HttpResponse::ServiceUnavailable()
+            .append_header(("X-Worker-Id", worker_id))
+            .content_type("text/html; charset=utf-8")
+            .body("Worker out of memory"),
+        EvalError::TimeLimitExceeded => {
+            println!("Worker {} exceeded its time limit", worker_id);
+
+            HttpResponse::InternalServerError()
+                .append_header(("X-Worker-Id", worker_id))
+                .content_type("text/html; charset=utf-8")
+                .body("Timeout")
+        }
+        err => {
+            eprintln!("{}", err);
+
+            HttpResponse::InternalServerError()
+                .append_header(("X-Worker-Id", worker_id))
+                .content_type("text/html; charset=utf-8")
+                .body("Cannot create event, check your response type")
+        }
+    }
+}
+
+/// Answer for an event the worker did not respond to, forwarded to the
+/// origin when one is configured
+async fn no_response(
+    origin: &Option<Origin>,
+    worker_id: usize,
+    reason: NoResponse,
+    req: &HttpRequest,
+    body: Bytes,
+) -> HttpResponse {
+    println!("Worker {} did not respond: {}", worker_id, reason);
+
+    let origin = match (reason, origin) {
+        (NoResponse::NotHandled | NoResponse::PassThrough, Some(origin)) => origin,
+        (NoResponse::TimedOut, _) => {
+            return HttpResponse::GatewayTimeout()
+                .append_header(("X-Worker-Id", worker_id))
+                .content_type("text/html; charset=utf-8")
+                .body("Worker did not respond in time");
+        }
+        _ => {
             return HttpResponse::InternalServerError()
                 .append_header(("X-Worker-Id", worker_id))
                 .content_type("text/html; charset=utf-8")
-                .body("Timeout");
+                .body("Worker did not respond");
         }
     };
-    println!("Time RES: {:?}", time.elapsed().unwrap());
 
-    let response = match response {
-        Some(response) => response.into(),
-        None => HttpResponse::InternalServerError()
-            .append_header(("X-Worker-Id", worker_id))
+    let request = origin_request(req, body, &origin.url, origin.timeout);
+
+    match origin.client.fetch(request).await {
+        Ok(response) => response.into(),
+        Err(err) => {
+            eprintln!("Cannot reach origin {}: {}", origin.url, err);
+
+            HttpResponse::BadGateway()
+                .content_type("text/html; charset=utf-8")
+                .body("Cannot reach origin")
+        }
+    }
+}
+
+/// Id of the request, from the `X-Request-Id` header if given
+fn request_id(req: &HttpRequest) -> String {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+    match req.headers().get("x-request-id").map(|id| id.to_str()) {
+        Some(Ok(id)) => id.to_string(),
+        _ => NEXT_ID.fetch_add(1, Ordering::Relaxed).to_string(),
+    }
+}
+
+async fn handle_request(
+    pool: Data<RuntimePool<FetchJob>>,
+    origin: Data<Option<Origin>>,
+    req: HttpRequest,
+    body: Bytes,
+) -> HttpResponse {
+    let (sender, receiver) = oneshot::channel();
+
+    let job = FetchJob {
+        request_id: request_id(&req),
+        request: (req.clone(), body.clone()).into(),
+        sender,
+    };
+
+    if let Err(err) = pool.dispatch(job) {
+        println!("Cannot dispatch request: {}", err);
+
+        return HttpResponse::ServiceUnavailable()
+            .content_type("text/html; charset=utf-8")
+            .body("Server busy");
+    }
+
+    let response = match receiver.await {
+        Ok((worker_id, Ok(FetchOutcome::Response(response)))) => {
+            let mut response: HttpResponse = response.into();
+            let worker_id = actix_web::http::header::HeaderValue::from(worker_id);
+            response.headers_mut().insert(
+                actix_web::http::header::HeaderName::from_static("x-worker-id"),
+                worker_id,
+            );
+            response
+        }
+        Ok((worker_id, Ok(FetchOutcome::NoResponse(reason)))) => {
+            no_response(&origin, worker_id, reason, &req, body).await
+        }
+        Ok((worker_id, Err(err))) => error_response(worker_id, err),
+        Err(_) => HttpResponse::InternalServerError()
             .content_type("text/html; charset=utf-8")
             .body("Error"),
     };
@@ -69,26 +421,42 @@ async fn handle_request(data: Data<AppState>, req: HttpRequest) -> HttpResponse
     response
 }
 
-async fn serve(script: String) -> std::io::Result<()> {
-    let snapshot = match std::fs::read("snapshot.bin") {
-        Ok(snapshot) => Some(snapshot),
-        Err(_) => None,
-    };
-
-    let server = HttpServer::new(move || {
-        let snapshot = snapshot.clone();
+async fn serve(
+    config: WorkerConfig,
+    options: PoolOptions,
+    origin: Option<(String, Duration)>,
+) -> std::io::Result<()> {
+    let config = Arc::new(config);
 
-        let mut rt = JsRuntime::create_init(snapshot);
+    let pool = RuntimePool::new(
+        options,
+        {
+            let config = config.clone();
+            move || {
+                let config = config.clone();
+                async move { config.create_runtime().await }
+            }
+        },
+        move |worker_id, rt, job| {
+            let config = config.clone();
+            async move { handle_fetch(&config, worker_id, rt, job).await }
+        },
+    );
 
-        rt.eval(script.as_str()).unwrap();
+    let pool = Data::new(pool);
 
-        let rt = Arc::new(Mutex::new(rt));
+    let origin = Data::new(origin.map(|(url, timeout)| Origin {
+        url,
+        client: ReqwestClient::default(),
+        timeout,
+    }));
 
+    let server = HttpServer::new(move || {
         App::new()
-            .app_data(Data::new(AppState { rt }))
+            .app_data(pool.clone())
+            .app_data(origin.clone())
             .service(web::resource("/{path}*").to(handle_request))
     })
-    .workers(1) // Set number of workers to 1 to reduce logging noise
     .bind(("127.0.0.1", 3000))?
     .run();
 
@@ -97,6 +465,24 @@ async fn serve(script: String) -> std::io::Result<()> {
     server.await
 }
 
+/// Value of a `--name=<value>` option
+fn option_value<'a>(args: &'a [String], prefix: &str) -> Option<&'a str> {
+    args.iter().skip(2).find_map(|arg| arg.strip_prefix(prefix))
+}
+
+/// Numeric value of a `--name=<value>` option
+fn parse_option(args: &[String], prefix: &str) -> Option<u64> {
+    let value = option_value(args, prefix)?;
+
+    match value.parse() {
+        Ok(value) => Some(value),
+        Err(_) => {
+            eprintln!("Error: Invalid value for {}", prefix.trim_end_matches('='));
+            std::process::exit(1);
+        }
+    }
+}
+
 #[actix_web::main]
 
 async fn main() {
@@ -111,7 +497,60 @@ async fn main() {
         Some(path) => {
             let script = read_script_file(path);
 
-            match serve(script).await {
+            let snapshot = match std::fs::read("snapshot.bin") {
+                Ok(snapshot) => Some(snapshot),
+                Err(_) => None,
+            };
+
+            // --max-heap-size=<MiB>
+            let heap_limits = parse_option(&args, "--max-heap-size=")
+                .map(|size| HeapLimits::new(0, size as usize * 1024 * 1024));
+
+            // --time-limit=<ms>
+            let time_limit = parse_option(&args, "--time-limit=").unwrap_or(1000);
+            let time_limit = Duration::from_millis(time_limit);
+
+            // --response-timeout=<ms>
+            let response_timeout = parse_option(&args, "--response-timeout=").unwrap_or(30_000);
+            let response_timeout = Duration::from_millis(response_timeout);
+
+            // --grace-period=<ms>
+            let grace_period = parse_option(&args, "--grace-period=").unwrap_or(30_000);
+            let grace_period = Duration::from_millis(grace_period);
+
+            let config = WorkerConfig {
+                path: path.clone(),
+                script,
+                snapshot,
+                heap_limits,
+                time_limit,
+                response_timeout,
+                grace_period,
+                jitless: args.iter().any(|arg| arg == "--jitless"),
+            };
+
+            // --isolates=<N> --queue-size=<Q>
+            let isolates = parse_option(&args, "--isolates=")
+                .map(|isolates| isolates as usize)
+                .unwrap_or_else(|| {
+                    std::thread::available_parallelism()
+                        .map(|threads| threads.get())
+                        .unwrap_or(1)
+                });
+
+            let options = PoolOptions {
+                isolates,
+                queue_size: parse_option(&args, "--queue-size=").unwrap_or(64) as usize,
+            };
+
+            // --origin=<url>, receives the requests the worker does not respond to
+            // --origin-timeout=<ms>
+            let origin_timeout = parse_option(&args, "--origin-timeout=").unwrap_or(30_000);
+            let origin_timeout = Duration::from_millis(origin_timeout);
+            let origin =
+                option_value(&args, "--origin=").map(|url| (String::from(url), origin_timeout));
+
+            match serve(config, options, origin).await {
                 Ok(_) => (),
                 Err(e) => eprintln!("Error: {}", e),
             };
@@ -124,4 +563,193 @@ async fn main() {
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::handle_fetch;
+    use super::FetchJob;
+    use super::FetchOutcome;
+    use super::WorkerConfig;
+
+    use lib::core::EvalError;
+    use lib::core::HeapLimits;
+    use lib::fetch::JsRequest;
+    use lib::fetch::NoResponse;
+
+    use std::time::Duration;
+
+    use tokio::sync::oneshot;
+
+    #[tokio::test]
+    async fn worker_should_answer_after_heap_recycles() {
+        let config = WorkerConfig {
+            path: String::from("worker.js"),
+            script: String::from(
+                "addEventListener('fetch', (event) => {
+                    if (event.request.url.endsWith('/oom')) {
+                        const a = [];
+                        while (true) a.push(new Array(1024).fill('x'));
+                    }
+
+                    event.respondWith(new Response('ok'));
+                });",
+            ),
+            snapshot: None,
+            heap_limits: Some(HeapLimits::new(0, 20 * 1024 * 1024)),
+            time_limit: Duration::from_secs(5),
+            response_timeout: Duration::from_secs(5),
+            grace_period: Duration::from_secs(1),
+            jitless: false,
+        };
+
+        let mut rt = config.create_runtime().await;
+
+        for path in ["/oom", "/oom", "/"] {
+            let (sender, receiver) = oneshot::channel();
+
+            let job = FetchJob {
+                request: JsRequest::new(format!("http://localhost{}", path), String::from("GET")),
+                request_id: String::from("1"),
+                sender,
+            };
+
+            rt = handle_fetch(&config, 0, rt, job).await;
+
+            match receiver.await.unwrap() {
+                (_, Err(err)) => {
+                    assert_eq!(path, "/oom");
+                    assert_eq!(err, EvalError::HeapLimitExceeded);
+                }
+                (_, Ok(FetchOutcome::Response(response))) => {
+                    assert_eq!(path, "/");
+                    assert_eq!(response.body, b"ok");
+                }
+                (_, Ok(FetchOutcome::NoResponse(reason))) => panic!("No response: {}", reason),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn worker_should_not_wait_for_event_loop_without_response() {
+        let config = WorkerConfig {
+            path: String::from("worker.js"),
+            script: String::from(
+                "setInterval(() => {}, 10);
+                addEventListener('fetch', (event) => {
+                    event.waitUntil(new Promise((resolve) => setTimeout(resolve, 10)));
+                });",
+            ),
+            snapshot: None,
+            heap_limits: None,
+            time_limit: Duration::from_secs(5),
+            response_timeout: Duration::from_secs(5),
+            grace_period: Duration::from_secs(30),
+            jitless: false,
+        };
+
+        let rt = config.create_runtime().await;
+        let (sender, receiver) = oneshot::channel();
+
+        let job = FetchJob {
+            request: JsRequest::new(String::from("http://localhost/"), String::from("GET")),
+            request_id: String::from("1"),
+            sender,
+        };
+
+        // The interval keeps the event loop running for good
+        let handled =
+            tokio::time::timeout(Duration::from_secs(1), handle_fetch(&config, 0, rt, job));
+
+        assert!(handled.await.is_ok());
+        assert!(matches!(
+            receiver.await.unwrap(),
+            (_, Ok(FetchOutcome::NoResponse(NoResponse::NotHandled)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn worker_should_give_up_on_unsettled_response() {
+        let config = WorkerConfig {
+            path: String::from("worker.js"),
+            script: String::from(
+                "setInterval(() => {}, 10);
+                addEventListener('fetch', (event) => {
+                    event.respondWith(new Promise(() => {}));
+                });",
+            ),
+            snapshot: None,
+            heap_limits: None,
+            time_limit: Duration::from_secs(5),
+            response_timeout: Duration::from_millis(100),
+            grace_period: Duration::from_secs(30),
+            jitless: false,
+        };
+
+        let rt = config.create_runtime().await;
+        let (sender, receiver) = oneshot::channel();
+
+        let job = FetchJob {
+            request: JsRequest::new(String::from("http://localhost/"), String::from("GET")),
+            request_id: String::from("1"),
+            sender,
+        };
+
+        // Neither the response nor the event loop ever ends
+        let handled =
+            tokio::time::timeout(Duration::from_secs(1), handle_fetch(&config, 0, rt, job));
+
+        assert!(handled.await.is_ok());
+        assert!(matches!(
+            receiver.await.unwrap(),
+            (_, Ok(FetchOutcome::NoResponse(NoResponse::TimedOut)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn worker_should_abort_streams_outliving_grace_period() {
+        let config = WorkerConfig {
+            path: String::from("worker.js"),
+            script: String::from(
+                "setInterval(() => {}, 10);
+                addEventListener('fetch', (event) => {
+                    const body = new ReadableStream({
+                        start(controller) {
+                            controller.enqueue(new TextEncoder().encode('partial'));
+                        },
+                    });
+
+                    event.respondWith(new Response(body));
+                });",
+            ),
+            snapshot: None,
+            heap_limits: None,
+            time_limit: Duration::from_secs(5),
+            response_timeout: Duration::from_secs(5),
+            grace_period: Duration::from_millis(100),
+            jitless: false,
+        };
+
+        let rt = config.create_runtime().await;
+        let (sender, receiver) = oneshot::channel();
+
+        let job = FetchJob {
+            request: JsRequest::new(String::from("http://localhost/"), String::from("GET")),
+            request_id: String::from("1"),
+            sender,
+        };
+
+        let handled =
+            tokio::time::timeout(Duration::from_secs(1), handle_fetch(&config, 0, rt, job));
+        assert!(handled.await.is_ok());
+
+        let mut stream = match receiver.await.unwrap() {
+            (_, Ok(FetchOutcome::Response(response))) => response.stream.unwrap(),
+            _ => panic!("expected a streamed response"),
+        };
+
+        // The body is cut off with an error rather than left hanging
+        assert_eq!(stream.recv().await, Some(Ok(b"partial".to_vec())));
+        assert_eq!(
+            stream.recv().await,
+            Some(Err(String::from("Grace period exceeded")))
+        );
+    }
+}
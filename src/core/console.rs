@@ -0,0 +1,142 @@
+use std::cell::RefCell;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::Mutex;
+
+use chrono::NaiveDateTime;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogLevel {
+    Log,
+    Info,
+    Warn,
+    Debug,
+    Error,
+}
+
+impl LogLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogLevel::Log => "log",
+            LogLevel::Info => "info",
+            LogLevel::Warn => "warn",
+            LogLevel::Debug => "debug",
+            LogLevel::Error => "error",
+        }
+    }
+}
+
+impl std::str::FromStr for LogLevel {
+    type Err = ();
+
+    fn from_str(level: &str) -> Result<Self, Self::Err> {
+        match level {
+            "log" => Ok(LogLevel::Log),
+            "info" => Ok(LogLevel::Info),
+            "warn" => Ok(LogLevel::Warn),
+            "debug" => Ok(LogLevel::Debug),
+            "error" => Ok(LogLevel::Error),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A console call made by the worker
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogRecord {
+    pub level: LogLevel,
+    pub timestamp: NaiveDateTime,
+    pub worker_id: Option<String>,
+    pub request_id: Option<String>,
+    /// Arguments as inspected strings, strings are kept as is
+    pub args: Vec<String>,
+}
+
+/// Destination of the worker console output
+pub trait ConsoleSink {
+    fn log(&self, record: LogRecord);
+}
+
+/// Default sink, prints records to stdout
+pub struct StdoutSink;
+
+impl ConsoleSink for StdoutSink {
+    fn log(&self, record: LogRecord) {
+        let mut ids = String::new();
+
+        if let Some(worker_id) = &record.worker_id {
+            ids.push_str(&format!(" worker={}", worker_id));
+        }
+
+        if let Some(request_id) = &record.request_id {
+            ids.push_str(&format!(" request={}", request_id));
+        }
+
+        println!(
+            "[{:?}]{} console.{}: {}",
+            record.timestamp,
+            ids,
+            record.level.as_str(),
+            record.args.join(" ")
+        );
+    }
+}
+
+/// Appends records to a file, one JSON object per line
+pub struct JsonLinesSink {
+    file: Mutex<File>,
+}
+
+impl JsonLinesSink {
+    pub fn new<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(JsonLinesSink {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl ConsoleSink for JsonLinesSink {
+    fn log(&self, record: LogRecord) {
+        let line = serde_json::json!({
+            "level": record.level.as_str(),
+            "timestamp": record.timestamp.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+            "worker_id": record.worker_id,
+            "request_id": record.request_id,
+            "args": record.args,
+        });
+
+        // Single write per record so that lines of concurrent writers don't mix
+        let mut file = self.file.lock().unwrap();
+
+        if let Err(err) = file.write_all(format!("{}\n", line).as_bytes()) {
+            eprintln!("Failed to write console record: {}", err);
+        }
+    }
+}
+
+/// Keeps records in memory, clones share the same records
+#[derive(Clone, Default)]
+pub struct MemorySink {
+    records: Rc<RefCell<Vec<LogRecord>>>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn records(&self) -> Vec<LogRecord> {
+        self.records.borrow().clone()
+    }
+}
+
+impl ConsoleSink for MemorySink {
+    fn log(&self, record: LogRecord) {
+        self.records.borrow_mut().push(record);
+    }
+}
@@ -0,0 +1,532 @@
+use v8::HandleScope;
+use v8::Local;
+use v8::MapFnTo;
+
+use std::sync::OnceLock;
+
+use crate::utils;
+
+/// Encodings supported by TextDecoder
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    /// Also used for the "latin1" and "ascii" labels, per the Encoding spec
+    Windows1252,
+}
+
+impl Encoding {
+    /// Encoding of a label, labels are matched case-insensitively after
+    /// trimming ASCII whitespace
+    pub fn for_label(label: &str) -> Option<Encoding> {
+        let label = label
+            .trim_matches(|c: char| c.is_ascii_whitespace())
+            .to_ascii_lowercase();
+
+        match label.as_str() {
+            "unicode-1-1-utf-8" | "unicode11utf8" | "unicode20utf8" | "utf-8" | "utf8"
+            | "x-unicode20utf8" => Some(Encoding::Utf8),
+            "csunicode" | "iso-10646-ucs-2" | "ucs-2" | "unicode" | "unicodefeff" | "utf-16"
+            | "utf-16le" => Some(Encoding::Utf16Le),
+            "ansi_x3.4-1968" | "ascii" | "cp1252" | "cp819" | "csisolatin1" | "ibm819"
+            | "iso-8859-1" | "iso-ir-100" | "iso8859-1" | "iso88591" | "iso_8859-1"
+            | "iso_8859-1:1987" | "l1" | "latin1" | "us-ascii" | "windows-1252" | "x-cp1252" => {
+                Some(Encoding::Windows1252)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Windows1252 => "windows-1252",
+        }
+    }
+}
+
+/// Code points of windows-1252 bytes 0x80 to 0x9F, the other bytes map to
+/// the code point of the same value
+const WINDOWS_1252: [u16; 32] = [
+    0x20ac, 0x0081, 0x201a, 0x0192, 0x201e, 0x2026, 0x2020, 0x2021, 0x02c6, 0x2030, 0x0160, 0x2039,
+    0x0152, 0x008d, 0x017d, 0x008f, 0x0090, 0x2018, 0x2019, 0x201c, 0x201d, 0x2022, 0x2013, 0x2014,
+    0x02dc, 0x2122, 0x0161, 0x203a, 0x0153, 0x009d, 0x017e, 0x0178,
+];
+
+fn invalid(encoding: Encoding) -> String {
+    format!(
+        "The encoded data was not valid for encoding {}",
+        encoding.name()
+    )
+}
+
+fn decode_utf8(bytes: &[u8], fatal: bool, stream: bool) -> Result<(String, usize), String> {
+    let mut text = String::with_capacity(bytes.len());
+    let mut rest = bytes;
+
+    loop {
+        match std::str::from_utf8(rest) {
+            Ok(valid) => {
+                text.push_str(valid);
+                return Ok((text, 0));
+            }
+            Err(err) => {
+                let (valid, invalid_bytes) = rest.split_at(err.valid_up_to());
+                text.push_str(std::str::from_utf8(valid).unwrap());
+
+                match err.error_len() {
+                    // Incomplete sequence at the end, kept for the next chunk
+                    None if stream => return Ok((text, invalid_bytes.len())),
+                    _ if fatal => return Err(invalid(Encoding::Utf8)),
+                    None => {
+                        text.push(char::REPLACEMENT_CHARACTER);
+                        return Ok((text, 0));
+                    }
+                    // Maximal subparts are replaced one at a time
+                    Some(len) => {
+                        text.push(char::REPLACEMENT_CHARACTER);
+                        rest = &invalid_bytes[len..];
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn decode_utf16le(bytes: &[u8], fatal: bool, stream: bool) -> Result<(String, usize), String> {
+    let mut units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+
+    let mut pending = bytes.len() % 2;
+
+    // A lead surrogate may be completed by the next chunk
+    if stream && matches!(units.last(), Some(0xd800..=0xdbff)) {
+        units.pop();
+        pending += 2;
+    }
+
+    let mut text = String::with_capacity(units.len());
+
+    for c in char::decode_utf16(units) {
+        match c {
+            Ok(c) => text.push(c),
+            Err(_) if fatal => return Err(invalid(Encoding::Utf16Le)),
+            Err(_) => text.push(char::REPLACEMENT_CHARACTER),
+        }
+    }
+
+    if pending == 1 && !stream {
+        if fatal {
+            return Err(invalid(Encoding::Utf16Le));
+        }
+
+        text.push(char::REPLACEMENT_CHARACTER);
+        pending = 0;
+    }
+
+    Ok((text, pending))
+}
+
+fn decode_windows1252(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&byte| match byte {
+            0x80..=0x9f => char::from_u32(WINDOWS_1252[byte as usize - 0x80] as u32).unwrap(),
+            byte => byte as char,
+        })
+        .collect()
+}
+
+/// Decode `bytes`, returns the text and the number of bytes left at the end
+/// when `stream` is set and they are the start of an incomplete sequence.
+/// Byte order marks are left to the caller
+pub fn decode(
+    encoding: Encoding,
+    bytes: &[u8],
+    fatal: bool,
+    stream: bool,
+) -> Result<(String, usize), String> {
+    match encoding {
+        Encoding::Utf8 => decode_utf8(bytes, fatal, stream),
+        Encoding::Utf16Le => decode_utf16le(bytes, fatal, stream),
+        Encoding::Windows1252 => Ok((decode_windows1252(bytes), 0)),
+    }
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_value(c: u8) -> Option<u32> {
+    match c {
+        b'A'..=b'Z' => Some((c - b'A') as u32),
+        b'a'..=b'z' => Some((c - b'a') as u32 + 26),
+        b'0'..=b'9' => Some((c - b'0') as u32 + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
+
+/// Forgiving-base64 decode of the Infra spec: ASCII whitespace is ignored,
+/// padding is optional and unused trailing bits may be set
+pub fn base64_decode(data: &str) -> Option<Vec<u8>> {
+    let mut data: Vec<u8> = data
+        .bytes()
+        .filter(|c| !matches!(c, b'\t' | b'\n' | b'\x0c' | b'\r' | b' '))
+        .collect();
+
+    if data.len() % 4 == 0 {
+        for _ in 0..2 {
+            if data.last() == Some(&b'=') {
+                data.pop();
+            }
+        }
+    }
+
+    if data.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(data.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for c in data {
+        buffer = (buffer << 6) | base64_value(c)?;
+        bits += 6;
+
+        if bits == 24 {
+            bytes.extend_from_slice(&buffer.to_be_bytes()[1..]);
+            buffer = 0;
+            bits = 0;
+        }
+    }
+
+    match bits {
+        12 => bytes.push((buffer >> 4) as u8),
+        18 => bytes.extend_from_slice(&((buffer >> 2) as u16).to_be_bytes()),
+        _ => {}
+    }
+
+    Some(bytes)
+}
+
+/// Padded base64 encoding
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let buffer = chunk.iter().enumerate().fold(0u32, |buffer, (i, byte)| {
+            buffer | (*byte as u32) << (16 - 8 * i)
+        });
+
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => {
+                    let index = (buffer >> (18 - 6 * i)) & 0x3f;
+                    encoded.push(BASE64_ALPHABET[index as usize] as char);
+                }
+                false => encoded.push('='),
+            }
+        }
+    }
+
+    encoded
+}
+
+/// Bytes of an ArrayBuffer or ArrayBufferView, empty for undefined
+fn buffer_source_bytes(value: Local<v8::Value>) -> Option<Vec<u8>> {
+    if value.is_undefined() {
+        return Some(vec![]);
+    }
+
+    if let Ok(view) = Local::<v8::ArrayBufferView>::try_from(value) {
+        let mut bytes = vec![0; view.byte_length()];
+        view.copy_contents(&mut bytes);
+        return Some(bytes);
+    }
+
+    if let Ok(buffer) = Local::<v8::ArrayBuffer>::try_from(value) {
+        let store = buffer.get_backing_store();
+        let bytes = store[..buffer.byte_length()]
+            .iter()
+            .map(|byte| byte.get())
+            .collect();
+        return Some(bytes);
+    }
+
+    None
+}
+
+/// __encode(string): UTF-8 bytes of a string in a Uint8Array, lone
+/// surrogates are replaced by U+FFFD
+pub(crate) fn encode(
+    scope: &mut HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut ret: v8::ReturnValue,
+) {
+    let bytes = args.get(0).to_rust_string_lossy(scope).into_bytes();
+    let length = bytes.len();
+
+    let store = v8::ArrayBuffer::new_backing_store_from_vec(bytes).make_shared();
+    let buffer = v8::ArrayBuffer::with_backing_store(scope, &store);
+
+    ret.set(
+        v8::Uint8Array::new(scope, buffer, 0, length)
+            .unwrap()
+            .into(),
+    );
+}
+
+/// __encodeInto(string, destination): write as many whole characters of the
+/// string as fit in the Uint8Array, returns `{ read, written }` with `read`
+/// in UTF-16 code units
+pub(crate) fn encode_into(
+    scope: &mut HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut ret: v8::ReturnValue,
+) {
+    let string = args.get(0).to_rust_string_lossy(scope);
+
+    let destination = match Local::<v8::Uint8Array>::try_from(args.get(1)) {
+        Ok(destination) => destination,
+        Err(_) => {
+            utils::throw_type_error(scope, "The destination must be a Uint8Array");
+            return;
+        }
+    };
+
+    let offset = destination.byte_offset();
+    let capacity = destination.byte_length();
+    let store = destination.buffer(scope).unwrap().get_backing_store();
+    let target = &store[offset..offset + capacity];
+
+    let mut read = 0;
+    let mut written = 0;
+    let mut buffer = [0; 4];
+
+    for c in string.chars() {
+        let encoded = c.encode_utf8(&mut buffer).as_bytes();
+
+        if written + encoded.len() > capacity {
+            break;
+        }
+
+        for (cell, byte) in target[written..].iter().zip(encoded) {
+            cell.set(*byte);
+        }
+
+        read += c.len_utf16();
+        written += encoded.len();
+    }
+
+    let result = v8::Object::new(scope);
+    let read = v8::Number::new(scope, read as f64).into();
+    let written = v8::Number::new(scope, written as f64).into();
+
+    utils::assign(scope, result, "read", read);
+    utils::assign(scope, result, "written", written);
+
+    ret.set(result.into());
+}
+
+/// __encodingForLabel(label): name of the encoding of a label, undefined if
+/// it is not supported
+pub(crate) fn encoding_for_label(
+    scope: &mut HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut ret: v8::ReturnValue,
+) {
+    let label = args.get(0).to_rust_string_lossy(scope);
+
+    if let Some(encoding) = Encoding::for_label(&label) {
+        ret.set(v8::String::new(scope, encoding.name()).unwrap().into());
+    }
+}
+
+/// __decode(bytes, encoding, fatal, stream): `[text, pending]` where
+/// `pending` is the number of trailing bytes to give again with the next chunk
+pub(crate) fn decode_callback(
+    scope: &mut HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut ret: v8::ReturnValue,
+) {
+    let bytes = match buffer_source_bytes(args.get(0)) {
+        Some(bytes) => bytes,
+        None => {
+            utils::throw_type_error(scope, "The input must be an ArrayBuffer or a view");
+            return;
+        }
+    };
+
+    let label = args.get(1).to_rust_string_lossy(scope);
+    let encoding = Encoding::for_label(&label).unwrap_or(Encoding::Utf8);
+    let fatal = args.get(2).boolean_value(scope);
+    let stream = args.get(3).boolean_value(scope);
+
+    let (text, pending) = match decode(encoding, &bytes, fatal, stream) {
+        Ok(result) => result,
+        Err(message) => {
+            utils::throw_type_error(scope, &message);
+            return;
+        }
+    };
+
+    let text = v8::String::new(scope, &text).unwrap().into();
+    let pending = v8::Integer::new_from_unsigned(scope, pending as u32).into();
+
+    ret.set(v8::Array::new_with_elements(scope, &[text, pending]).into());
+}
+
+/// __atob(data): binary string of the decoded data, null if it is not
+/// valid base64
+pub(crate) fn atob(
+    scope: &mut HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut ret: v8::ReturnValue,
+) {
+    let data = args.get(0).to_rust_string_lossy(scope);
+
+    match base64_decode(&data) {
+        Some(bytes) => {
+            let decoded =
+                v8::String::new_from_one_byte(scope, &bytes, v8::NewStringType::Normal).unwrap();
+            ret.set(decoded.into());
+        }
+        None => ret.set_null(),
+    }
+}
+
+/// __btoa(data): base64 encoding of a binary string, null if it has
+/// characters outside of the Latin-1 range
+pub(crate) fn btoa(
+    scope: &mut HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut ret: v8::ReturnValue,
+) {
+    let data = args.get(0).to_rust_string_lossy(scope);
+
+    // Lone surrogates are lossily replaced by U+FFFD, which is rejected too
+    let bytes: Option<Vec<u8>> = data.chars().map(|c| u8::try_from(c).ok()).collect();
+
+    match bytes {
+        Some(bytes) => {
+            let encoded = v8::String::new(scope, &base64_encode(&bytes)).unwrap();
+            ret.set(encoded.into());
+        }
+        None => ret.set_null(),
+    }
+}
+
+/// Natives of this module, they are part of snapshots so isolates must be
+/// created with them
+pub(crate) fn external_references() -> &'static v8::ExternalReferences {
+    static REFERENCES: OnceLock<v8::ExternalReferences> = OnceLock::new();
+
+    REFERENCES.get_or_init(|| {
+        v8::ExternalReferences::new(&[
+            v8::ExternalReference {
+                function: encode.map_fn_to(),
+            },
+            v8::ExternalReference {
+                function: encode_into.map_fn_to(),
+            },
+            v8::ExternalReference {
+                function: encoding_for_label.map_fn_to(),
+            },
+            v8::ExternalReference {
+                function: decode_callback.map_fn_to(),
+            },
+            v8::ExternalReference {
+                function: atob.map_fn_to(),
+            },
+            v8::ExternalReference {
+                function: btoa.map_fn_to(),
+            },
+        ])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::base64_decode;
+    use super::base64_encode;
+    use super::decode;
+    use super::Encoding;
+
+    #[test]
+    fn encoding_should_resolve_labels() {
+        assert_eq!(Encoding::for_label(" UTF8\n"), Some(Encoding::Utf8));
+        assert_eq!(Encoding::for_label("utf-16"), Some(Encoding::Utf16Le));
+        assert_eq!(Encoding::for_label("Latin1"), Some(Encoding::Windows1252));
+        assert_eq!(Encoding::for_label("utf-16be"), None);
+    }
+
+    #[test]
+    fn decode_should_replace_or_reject_invalid_sequences() {
+        let bytes = b"a\xf0\x9f\x98b\xffc";
+
+        assert_eq!(
+            decode(Encoding::Utf8, bytes, false, false),
+            Ok((String::from("a\u{fffd}b\u{fffd}c"), 0))
+        );
+        assert!(decode(Encoding::Utf8, bytes, true, false).is_err());
+
+        // Lone surrogate, then an odd trailing byte
+        let bytes = [0x61, 0x00, 0x00, 0xdc, 0x62];
+
+        assert_eq!(
+            decode(Encoding::Utf16Le, &bytes, false, false),
+            Ok((String::from("a\u{fffd}\u{fffd}"), 0))
+        );
+        assert_eq!(
+            decode(Encoding::Windows1252, b"\x80\xe9", false, false),
+            Ok((String::from("€é"), 0))
+        );
+    }
+
+    #[test]
+    fn decode_should_keep_incomplete_sequences_when_streaming() {
+        assert_eq!(
+            decode(Encoding::Utf8, b"a\xe2\x82", false, true),
+            Ok((String::from("a"), 2))
+        );
+        assert_eq!(
+            decode(
+                Encoding::Utf16Le,
+                &[0x61, 0x00, 0x3d, 0xd8, 0x00],
+                false,
+                true
+            ),
+            Ok((String::from("a"), 3))
+        );
+    }
+
+    #[test]
+    fn base64_should_decode_forgivingly() {
+        assert_eq!(base64_decode("aGVsbG8="), Some(b"hello".to_vec()));
+        assert_eq!(base64_decode(" aGVs\tbG8\n"), Some(b"hello".to_vec()));
+        assert_eq!(base64_decode("YQ"), Some(b"a".to_vec()));
+        assert_eq!(base64_decode("YR=="), Some(b"a".to_vec()));
+        assert_eq!(base64_decode(""), Some(vec![]));
+
+        assert_eq!(base64_decode("YQ="), None);
+        assert_eq!(base64_decode("YQ==="), None);
+        assert_eq!(base64_decode("Y"), None);
+        assert_eq!(base64_decode("Y=Q="), None);
+        assert_eq!(base64_decode("aGVsbG8\u{a0}"), None);
+        assert_eq!(base64_decode("aGV-bG8="), None);
+    }
+
+    #[test]
+    fn base64_should_encode_with_padding() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"a"), "YQ==");
+        assert_eq!(base64_encode(b"ab"), "YWI=");
+        assert_eq!(base64_encode(b"abc"), "YWJj");
+        assert_eq!(base64_encode(&[0xff, 0xfe, 0x00, 0x80]), "//4AgA==");
+    }
+}
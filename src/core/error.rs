@@ -0,0 +1,125 @@
+use v8::HandleScope;
+use v8::Local;
+use v8::Value;
+
+#[derive(Debug, PartialEq)]
+pub enum EvalError {
+    CompileError(JsError),
+    RuntimeError(JsError),
+    ConversionError,
+    ResolutionError(String),
+    HeapLimitExceeded,
+    TimeLimitExceeded,
+    ExtensionsWithSnapshot,
+}
+
+impl std::fmt::Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EvalError::CompileError(error) | EvalError::RuntimeError(error) => {
+                write!(f, "{}", error)
+            }
+            EvalError::ResolutionError(message) => write!(f, "{}", message),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+/// Exception thrown by a script, with its location when known
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JsError {
+    pub message: String,
+    pub stack: Option<String>,
+    pub script_name: Option<String>,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub source_line: Option<String>,
+}
+
+impl JsError {
+    /// Build an error from the exception caught by `tc`
+    pub(crate) fn from_try_catch(tc: &mut v8::TryCatch<HandleScope>) -> JsError {
+        let exception = match tc.exception() {
+            Some(exception) => exception,
+            None => {
+                return JsError {
+                    message: String::from("Uncaught exception"),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let message = match tc.message() {
+            Some(message) => message,
+            None => v8::Exception::create_message(tc, exception),
+        };
+
+        Self::from_message(tc, exception, message)
+    }
+
+    /// Build an error from a thrown value, e.g. a promise rejection reason
+    pub(crate) fn from_exception(scope: &mut HandleScope, exception: Local<Value>) -> JsError {
+        let message = v8::Exception::create_message(scope, exception);
+
+        Self::from_message(scope, exception, message)
+    }
+
+    fn from_message(
+        scope: &mut HandleScope,
+        exception: Local<Value>,
+        message: Local<v8::Message>,
+    ) -> JsError {
+        let stack = match exception.is_native_error() {
+            true => {
+                let exception = exception.to_object(scope).unwrap();
+                let stack = crate::utils::get(scope, exception, "stack");
+
+                match stack.is_string() {
+                    true => Some(stack.to_rust_string_lossy(scope)),
+                    false => None,
+                }
+            }
+            false => None,
+        };
+
+        let script_name = message
+            .get_script_resource_name(scope)
+            .filter(|name| name.is_string())
+            .map(|name| name.to_rust_string_lossy(scope));
+
+        let source_line = message
+            .get_source_line(scope)
+            .map(|line| line.to_rust_string_lossy(scope));
+
+        JsError {
+            message: message.get(scope).to_rust_string_lossy(scope),
+            stack,
+            script_name,
+            line: message.get_line_number(scope),
+            column: Some(message.get_start_column() + 1),
+            source_line,
+        }
+    }
+}
+
+impl std::fmt::Display for JsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+
+        if let (Some(source_line), Some(column)) = (&self.source_line, self.column) {
+            write!(f, "\n{}\n{}^", source_line, " ".repeat(column - 1))?;
+        }
+
+        match (&self.stack, &self.script_name, self.line, self.column) {
+            (Some(stack), _, _, _) => write!(f, "\n{}", stack),
+            (None, Some(name), Some(line), Some(column)) => {
+                write!(f, "\n    at {}:{}:{}", name, line, column)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for JsError {}
@@ -0,0 +1,150 @@
+use std::ffi::c_void;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Heap size limits of an isolate, in bytes
+#[derive(Debug, Clone, Copy)]
+pub struct HeapLimits {
+    pub initial: usize,
+    pub max: usize,
+}
+
+impl HeapLimits {
+    pub fn new(initial: usize, max: usize) -> Self {
+        HeapLimits { initial, max }
+    }
+}
+
+/// State shared with the near heap limit callback
+pub(crate) struct HeapState {
+    handle: v8::IsolateHandle,
+    exhausted: AtomicBool,
+}
+
+impl HeapState {
+    pub fn new(handle: v8::IsolateHandle) -> Self {
+        HeapState {
+            handle,
+            exhausted: AtomicBool::new(false),
+        }
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted.load(Ordering::SeqCst)
+    }
+}
+
+/// Terminate execution instead of letting V8 abort the process on OOM
+pub(crate) extern "C" fn near_heap_limit_callback(
+    data: *mut c_void,
+    current_heap_limit: usize,
+    _initial_heap_limit: usize,
+) -> usize {
+    let state = unsafe { &*(data as *const HeapState) };
+
+    if !state.exhausted.swap(true, Ordering::SeqCst) {
+        println!(
+            "Heap limit reached ({} bytes), terminating execution",
+            current_heap_limit
+        );
+
+        state.handle.terminate_execution();
+    }
+
+    // Leave room for the termination to unwind the stack
+    current_heap_limit * 2
+}
+
+#[derive(Default)]
+struct WatchdogState {
+    deadline: Option<Instant>,
+    timed_out: bool,
+    shutdown: bool,
+}
+
+type WatchdogShared = Arc<(Mutex<WatchdogState>, Condvar)>;
+
+/// Terminates executions exceeding a wall-clock budget, from its own thread
+#[derive(Default)]
+pub(crate) struct Watchdog {
+    limit: Option<Duration>,
+    shared: WatchdogShared,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watchdog {
+    /// Set the budget of each execution, the thread is spawned on first use
+    pub fn set_limit(&mut self, handle: v8::IsolateHandle, limit: Option<Duration>) {
+        self.limit = limit;
+
+        if limit.is_some() && self.thread.is_none() {
+            let shared = self.shared.clone();
+            self.thread = Some(std::thread::spawn(move || watch(handle, shared)));
+        }
+    }
+
+    /// Arm the watchdog before entering JS
+    pub fn start(&self) {
+        if let Some(limit) = self.limit {
+            let (state, cvar) = &*self.shared;
+            let mut state = state.lock().unwrap();
+
+            state.deadline = Some(Instant::now() + limit);
+            state.timed_out = false;
+
+            cvar.notify_one();
+        }
+    }
+
+    /// Disarm the watchdog, returns whether execution was terminated
+    pub fn stop(&self) -> bool {
+        let (state, _) = &*self.shared;
+        let mut state = state.lock().unwrap();
+
+        state.deadline = None;
+        std::mem::take(&mut state.timed_out)
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            let (state, cvar) = &*self.shared;
+            state.lock().unwrap().shutdown = true;
+            cvar.notify_one();
+
+            thread.join().unwrap();
+        }
+    }
+}
+
+fn watch(handle: v8::IsolateHandle, shared: WatchdogShared) {
+    let (state, cvar) = &*shared;
+    let mut state = state.lock().unwrap();
+
+    while !state.shutdown {
+        state = match state.deadline {
+            None => cvar.wait(state).unwrap(),
+            Some(deadline) => {
+                let now = Instant::now();
+
+                if now < deadline {
+                    cvar.wait_timeout(state, deadline - now).unwrap().0
+                } else {
+                    println!("Time limit exceeded, terminating execution");
+
+                    handle.terminate_execution();
+                    state.timed_out = true;
+                    state.deadline = None;
+                    state
+                }
+            }
+        };
+    }
+}
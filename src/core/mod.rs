@@ -1,20 +1,64 @@
+pub mod console;
+mod encoding;
+mod error;
+mod limits;
 mod message;
+pub mod module;
+pub(crate) mod ops;
+mod options;
+mod report;
 mod runtime;
+mod timers;
+mod url;
+
+pub use console::ConsoleSink;
+pub use console::LogRecord;
+pub use error::EvalError;
+pub use error::JsError;
+pub use limits::HeapLimits;
 
 pub use message::RuntimeBasicMessage;
 pub use message::RuntimeMessage;
+pub use module::FsModuleLoader;
+pub use module::ModuleLoader;
+pub use options::default_extensions;
+pub use options::Binding;
+pub use options::Extension;
+pub use options::JsRuntimeBuilder;
+pub use options::RuntimeOptions;
+pub use report::ErrorKind;
+pub use report::ErrorReport;
 pub use runtime::JsRuntime;
 
 pub struct JsState {
     pub handler: Option<v8::Global<v8::Function>>,
-    pub timer: Option<std::time::Instant>,
+    pub timers: timers::TimerQueue,
+    pub modules: module::ModuleMap,
+    pub loader: std::rc::Rc<dyn module::ModuleLoader>,
+    pub ops: ops::PendingOps,
+    pub rejections: report::Rejections,
+    pub errors: Vec<ErrorReport>,
+    pub request_id: Option<String>,
+    pub worker_id: Option<String>,
+    pub console: std::rc::Rc<dyn console::ConsoleSink>,
+    /// Client used by `fetch()`, created on first use if not set
+    pub http_client: Option<std::rc::Rc<dyn crate::fetch::HttpClient>>,
 }
 
 impl Default for JsState {
     fn default() -> Self {
         JsState {
             handler: None,
-            timer: None,
+            timers: timers::TimerQueue::default(),
+            modules: module::ModuleMap::default(),
+            loader: std::rc::Rc::new(module::FsModuleLoader),
+            ops: ops::PendingOps::default(),
+            rejections: report::Rejections::default(),
+            errors: Vec::new(),
+            request_id: None,
+            worker_id: None,
+            console: std::rc::Rc::new(console::StdoutSink),
+            http_client: None,
         }
     }
 }
@@ -23,8 +67,23 @@ pub type JsStateRef = std::rc::Rc<std::cell::RefCell<JsState>>;
 
 #[cfg(test)]
 mod tests {
-    use crate::core::runtime::EvalError;
+    use crate::core::console::LogLevel;
+    use crate::core::console::MemorySink;
     use crate::core::runtime::JsRuntime;
+    use crate::core::Binding;
+    use crate::core::ErrorKind;
+    use crate::core::EvalError;
+    use crate::core::Extension;
+    use crate::core::HeapLimits;
+    use crate::core::JsRuntimeBuilder;
+    use crate::core::ModuleLoader;
+    use crate::fetch::FetchResponse;
+    use crate::fetch::JsRequest;
+    use crate::fetch::MockHttpClient;
+    use crate::fetch::NoResponse;
+    use crate::fetch::RuntimeFetchMessage;
+
+    use std::time::Duration;
 
     /// eval should not panic when js exception is thrown
     #[test]
@@ -33,8 +92,7 @@ mod tests {
 
         let result = rt.eval("throw new Error('test')");
 
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), EvalError::RuntimeError);
+        assert!(matches!(result, Err(EvalError::RuntimeError(_))));
     }
 
     /// eval should not panic when js exception is thrown
@@ -44,8 +102,1178 @@ mod tests {
 
         let result = rt.eval("}");
 
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), EvalError::CompileError);
+        assert!(matches!(result, Err(EvalError::CompileError(_))));
+    }
+
+    #[test]
+    fn rt_should_report_error_location() {
+        let mut rt = JsRuntime::create_init(None);
+
+        let result = rt.execute_script("worker.js", "const a = 1;\n  throw new Error('boom');");
+
+        let error = match result {
+            Err(EvalError::RuntimeError(error)) => error,
+            _ => panic!("expected a runtime error"),
+        };
+
+        assert_eq!(error.message, "Uncaught Error: boom");
+        assert_eq!(error.script_name.as_deref(), Some("worker.js"));
+        assert_eq!(error.line, Some(2));
+        assert_eq!(error.column, Some(3));
+        assert_eq!(
+            error.source_line.as_deref(),
+            Some("  throw new Error('boom');")
+        );
+        assert!(error.stack.unwrap().contains("at worker.js:2:9"));
+    }
+
+    #[test]
+    fn rt_should_report_syntax_error_location() {
+        let mut rt = JsRuntime::create_init(None);
+
+        let result = rt.execute_script("worker.js", "let x = ;");
+
+        let error = match result {
+            Err(EvalError::CompileError(error)) => error,
+            _ => panic!("expected a compile error"),
+        };
+
+        assert!(error.message.starts_with("Uncaught SyntaxError"));
+        assert_eq!(error.line, Some(1));
+        assert_eq!(error.column, Some(9));
+    }
+
+    #[test]
+    fn rt_should_terminate_on_heap_limit() {
+        let limits = HeapLimits::new(0, 20 * 1024 * 1024);
+        let mut rt = JsRuntime::create_init_with_limits(None, Some(limits));
+
+        let result = rt.eval("const a = []; while (true) a.push(new Array(1024).fill('x'));");
+
+        assert_eq!(result, Err(EvalError::HeapLimitExceeded));
+        assert!(rt.is_heap_exhausted());
+    }
+
+    #[test]
+    fn rt_should_terminate_on_time_limit() {
+        let mut rt = JsRuntime::create_init(None);
+        rt.set_time_limit(Some(Duration::from_millis(50)));
+
+        let result = rt.eval("while (true) {}");
+
+        assert_eq!(result, Err(EvalError::TimeLimitExceeded));
+
+        // Runtime should remain usable
+        assert_eq!(rt.eval("1 + 1").unwrap(), String::from("2"));
+    }
+
+    #[tokio::test]
+    async fn rt_should_terminate_microtasks_on_time_limit() {
+        let mut rt = JsRuntime::create_init(None);
+        rt.set_time_limit(Some(Duration::from_millis(50)));
+
+        // The callback returns at once, the chain spins in the checkpoint after it
+        rt.eval(
+            "setTimeout(() => {
+                const loop = () => Promise.resolve().then(loop);
+                loop();
+            }, 0)",
+        )
+        .unwrap();
+
+        let result = rt.run_event_loop().await;
+
+        assert_eq!(result, Err(EvalError::TimeLimitExceeded));
+    }
+
+    #[tokio::test]
+    async fn rt_should_run_timers_in_order() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.eval(
+            "globalThis.fired = [];
+            setTimeout(() => fired.push('c'), 20);
+            setTimeout(() => fired.push('a'), 10);
+            setTimeout((name) => fired.push(name), 10, 'b');
+            setTimeout(() => fired.push('first'));
+            clearTimeout(setTimeout(() => fired.push('cleared'), 5));",
+        )
+        .unwrap();
+
+        rt.run_event_loop().await.unwrap();
+
+        let result = rt.eval("fired.join()").unwrap();
+
+        assert_eq!(result, String::from("first,a,b,c"));
+    }
+
+    #[tokio::test]
+    async fn rt_should_clear_interval_from_callback() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.eval(
+            "globalThis.ticks = 0;
+            const id = setInterval(() => {
+                if (++ticks === 3) clearInterval(id);
+            }, 5);",
+        )
+        .unwrap();
+
+        rt.run_event_loop().await.unwrap();
+
+        assert_eq!(rt.eval("ticks").unwrap(), String::from("3"));
+    }
+
+    #[tokio::test]
+    async fn rt_should_clamp_nested_timers() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.eval(
+            "globalThis.depth = 0;
+            const nest = () => { if (++depth < 10) setTimeout(nest, 0); };
+            setTimeout(nest, 0);",
+        )
+        .unwrap();
+
+        let start = std::time::Instant::now();
+        rt.run_event_loop().await.unwrap();
+
+        // Levels past the 5th are clamped to 4ms
+        assert!(start.elapsed() >= Duration::from_millis(16));
+        assert_eq!(rt.eval("depth").unwrap(), String::from("10"));
+    }
+
+    #[tokio::test]
+    async fn rt_should_report_unhandled_rejection() {
+        let mut rt = JsRuntime::create_init(None);
+        rt.set_request_id(Some(String::from("42")));
+
+        rt.eval(
+            "Promise.reject(new Error('lost'));
+            const late = Promise.reject(new Error('late'));
+            late.catch(() => {});",
+        )
+        .unwrap();
+        rt.run_event_loop().await.unwrap();
+
+        let reports = rt.take_error_reports();
+
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].kind, ErrorKind::UnhandledRejection);
+        assert_eq!(reports[0].error.message, "Uncaught Error: lost");
+        assert_eq!(reports[0].request_id.as_deref(), Some("42"));
+    }
+
+    #[tokio::test]
+    async fn rt_should_dispatch_error_events() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.eval(
+            "globalThis.caught = [];
+            addEventListener('error', (event) => caught.push(event.error.message));
+            addEventListener('unhandledrejection', (event) => {
+                caught.push(event.reason);
+                event.preventDefault();
+            });
+            setTimeout(() => { throw new Error('timer'); });
+            Promise.reject('reason');",
+        )
+        .unwrap();
+        rt.run_event_loop().await.unwrap();
+
+        assert_eq!(rt.eval("caught.join()").unwrap(), "reason,timer");
+
+        // Only the exception was not prevented
+        let reports = rt.take_error_reports();
+
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].kind, ErrorKind::UncaughtException);
+        assert_eq!(reports[0].error.message, "Uncaught Error: timer");
+    }
+
+    #[test]
+    fn rt_should_log_to_console_sink() {
+        let sink = MemorySink::new();
+
+        let mut rt = JsRuntime::create_init(None);
+        rt.set_console_sink(sink.clone());
+        rt.set_worker_id(Some(String::from("worker")));
+        rt.set_request_id(Some(String::from("42")));
+
+        rt.eval("console.warn('value:', { a: 1 }, [1, 2])").unwrap();
+
+        let records = sink.records();
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].level, LogLevel::Warn);
+        assert_eq!(records[0].worker_id.as_deref(), Some("worker"));
+        assert_eq!(records[0].request_id.as_deref(), Some("42"));
+        assert_eq!(records[0].args, vec!["value:", "{a: 1}", "[1, 2]"]);
+    }
+
+    #[test]
+    fn rt_should_log_cyclic_and_throwing_objects() {
+        let sink = MemorySink::new();
+
+        let mut rt = JsRuntime::create_init(None);
+        rt.set_console_sink(sink.clone());
+
+        rt.eval(
+            r#"
+            const a = { b: 1 };
+            a.self = a;
+            const c = { get d() { throw new Error("getter") } };
+            console.log(a, c, [[[[[[1]]]]]]);
+            console.log(globalThis);
+            "#,
+        )
+        .unwrap();
+
+        let records = sink.records();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].args,
+            vec![
+                "{b: 1, self: [Circular]}",
+                "{d: [Exception]}",
+                "[[[[[Array]]]]]"
+            ]
+        );
+    }
+
+    #[test]
+    fn rt_should_build_with_options() {
+        fn answer(
+            scope: &mut v8::HandleScope,
+            _args: v8::FunctionCallbackArguments,
+            mut rv: v8::ReturnValue,
+        ) {
+            rv.set(v8::Integer::new(scope, 42).into());
+        }
+
+        let mut rt = JsRuntimeBuilder::new()
+            .extension(Extension::new(
+                "greet.js",
+                "const greet = (name) => `Hello ${name}`;",
+            ))
+            .binding("answer", Binding::function(answer))
+            .binding("version", Binding::String(String::from("1.0")))
+            .time_limit(Duration::from_millis(50))
+            .build()
+            .unwrap();
+
+        assert_eq!(rt.eval("greet(version) + answer()").unwrap(), "Hello 1.042");
+        assert_eq!(
+            rt.eval("while (true) {}"),
+            Err(EvalError::TimeLimitExceeded)
+        );
+    }
+
+    #[test]
+    fn rt_should_build_without_extensions() {
+        let mut rt = JsRuntimeBuilder::new().extensions(vec![]).build().unwrap();
+
+        assert_eq!(rt.eval("typeof Headers").unwrap(), "undefined");
+    }
+
+    #[test]
+    fn rt_should_fail_to_build_with_faulty_extension() {
+        let result = JsRuntimeBuilder::new()
+            .extension(Extension::new("faulty.js", "throw new Error('faulty')"))
+            .build();
+
+        match result {
+            Err(EvalError::RuntimeError(error)) => {
+                assert_eq!(error.script_name.as_deref(), Some("faulty.js"))
+            }
+            _ => panic!("expected a runtime error"),
+        }
+    }
+
+    #[test]
+    fn rt_should_reject_extensions_with_snapshot() {
+        let result = JsRuntimeBuilder::new()
+            .snapshot(Vec::new())
+            .extension(Extension::new("greet.js", "const greet = () => 'hi';"))
+            .build();
+
+        assert!(matches!(result, Err(EvalError::ExtensionsWithSnapshot)));
+    }
+
+    #[tokio::test]
+    async fn rt_should_forward_request_headers_and_body() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.eval(
+            "addEventListener('fetch', (event) => {
+                const { method, headers } = event.request;
+                event.respondWith(event.request.arrayBuffer().then((body) => {
+                    const bytes = new Uint8Array(body).join();
+                    return new Response(`${method} ${headers.get('x-token')} ${bytes}`);
+                }));
+            });",
+        )
+        .unwrap();
+
+        let mut request = JsRequest::new(String::from("/"), String::from("POST"));
+        request.headers = vec![
+            (String::from("x-token"), String::from("a")),
+            (String::from("x-token"), String::from("b")),
+        ];
+        request.body = Some(vec![1, 2]);
+
+        let mut fetch = RuntimeFetchMessage::new(request);
+
+        rt.send_message(&mut fetch).unwrap();
+        rt.run_event_loop().await.unwrap();
+
+        let response = fetch.get_response().await.unwrap();
+
+        assert_eq!(response.body, b"POST a, b 1,2");
+    }
+
+    #[tokio::test]
+    async fn rt_should_return_response_headers_and_status_text() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.eval(
+            "addEventListener('fetch', (event) => {
+                const headers = new Headers([['set-cookie', 'a=1'], ['set-cookie', 'b=2']]);
+                event.respondWith(new Response('ok', { status: 201, statusText: 'Made', headers }));
+            });",
+        )
+        .unwrap();
+
+        let mut fetch =
+            RuntimeFetchMessage::new(JsRequest::new(String::from("/"), String::from("GET")));
+
+        rt.send_message(&mut fetch).unwrap();
+        rt.run_event_loop().await.unwrap();
+
+        let response = fetch.get_response().await.unwrap();
+
+        assert_eq!(response.status, 201);
+        assert_eq!(response.status_text.as_deref(), Some("Made"));
+        assert_eq!(
+            response.headers,
+            vec![
+                (String::from("set-cookie"), String::from("a=1")),
+                (String::from("set-cookie"), String::from("b=2")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn rt_should_read_and_clone_request_bodies() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.eval(
+            "globalThis.results = [];
+            const request = new Request('https://example.com/', {
+                method: 'post',
+                body: JSON.stringify({ a: 1 }),
+            });
+            const clone = request.clone();
+            request.json()
+                .then((json) => results.push(request.method, json.a, request.bodyUsed))
+                .then(() => request.text())
+                .catch((err) => results.push(err.name))
+                .then(() => clone.blob())
+                .then((blob) => results.push(blob.type, blob.size));",
+        )
+        .unwrap();
+
+        rt.run_event_loop().await.unwrap();
+
+        let result = rt.eval("globalThis.results.join(' ')").unwrap();
+        assert_eq!(result, "POST 1 true TypeError text/plain;charset=utf-8 7");
+    }
+
+    #[tokio::test]
+    async fn rt_should_parse_form_data_request_body() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.eval(
+            "addEventListener('fetch', (event) => {
+                event.respondWith(event.request.formData().then(async (form) => {
+                    const file = form.get('file');
+                    const text = await file.text();
+                    return new Response(`${form.get('a')} ${file.name} ${file.type} ${text}`);
+                }));
+            });",
+        )
+        .unwrap();
+
+        let mut request = JsRequest::new(String::from("/"), String::from("POST"));
+        request.headers = vec![(
+            String::from("content-type"),
+            String::from("multipart/form-data; boundary=xyz"),
+        )];
+        request.body = Some(
+            b"--xyz\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\n1\r\n\
+            --xyz\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\
+            Content-Type: text/plain\r\n\r\nhello\r\n--xyz--\r\n"
+                .to_vec(),
+        );
+
+        let mut fetch = RuntimeFetchMessage::new(request);
+
+        rt.send_message(&mut fetch).unwrap();
+        rt.run_event_loop().await.unwrap();
+
+        let response = fetch.get_response().await.unwrap();
+
+        assert_eq!(response.body, b"1 a.txt text/plain hello");
+    }
+
+    #[tokio::test]
+    async fn rt_should_round_trip_form_data_bodies() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.eval(
+            "const form = new FormData();
+            form.append('name', 'line 1\\nline 2');
+            form.append('file', new Blob([new Uint8Array([0, 255])]), 'data.bin');
+            const request = new Request('https://example.com/', { method: 'POST', body: form });
+            globalThis.type = request.headers.get('content-type');
+            request.formData().then(async (form) => {
+                const file = form.get('file');
+                const bytes = new Uint8Array(await file.arrayBuffer()).join();
+                globalThis.result = [JSON.stringify(form.get('name')), file.name, file.type, bytes].join(' ');
+            });
+            new Response(new URLSearchParams('x=1&x=2')).formData()
+                .then((form) => globalThis.urlencoded = form.getAll('x').join());",
+        )
+        .unwrap();
+
+        rt.run_event_loop().await.unwrap();
+
+        let content_type = rt.eval("globalThis.type").unwrap();
+        assert!(content_type.starts_with("multipart/form-data; boundary="));
+
+        let result = rt.eval("globalThis.result").unwrap();
+        assert_eq!(
+            result,
+            "\"line 1\\r\\nline 2\" data.bin application/octet-stream 0,255"
+        );
+
+        let result = rt.eval("globalThis.urlencoded").unwrap();
+        assert_eq!(result, "1,2");
+    }
+
+    #[tokio::test]
+    async fn rt_should_normalize_headers() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.eval(
+            "addEventListener('fetch', (event) => {
+                let immutable = false;
+                try { event.request.headers.set('x-token', 'c'); } catch { immutable = true; }
+                const headers = new Headers({ 'X-B': ' 2 ', 'Content-Type': 'text/plain' });
+                headers.append('x-a', '1');
+                headers.append('X-A', '3');
+                const token = event.request.headers.get('X-Token');
+                event.respondWith(new Response(`${immutable} ${token}`, { headers }));
+            });",
+        )
+        .unwrap();
+
+        let mut request = JsRequest::new(String::from("/"), String::from("GET"));
+        request.headers = vec![(String::from("X-Token"), String::from("a"))];
+
+        let mut fetch = RuntimeFetchMessage::new(request);
+
+        rt.send_message(&mut fetch).unwrap();
+        rt.run_event_loop().await.unwrap();
+
+        let response = fetch.get_response().await.unwrap();
+
+        assert_eq!(response.body, b"true a");
+        assert_eq!(
+            response.headers,
+            vec![
+                (String::from("content-type"), String::from("text/plain")),
+                (String::from("x-a"), String::from("1, 3")),
+                (String::from("x-b"), String::from("2")),
+            ]
+        );
+    }
+
+    #[test]
+    fn rt_should_allow_forbidden_header_names() {
+        let mut rt = JsRuntime::create_init(None);
+
+        let result = rt
+            .eval(
+                "const request = new Request('https://example.com/', {
+                    headers: { cookie: 'a=1', host: 'origin' },
+                });
+                const response = new Response(null, { headers: { 'set-cookie': 'b=2' } });
+                [
+                    request.headers.get('cookie'),
+                    request.headers.get('host'),
+                    response.headers.get('set-cookie'),
+                ].join(' ')",
+            )
+            .unwrap();
+
+        assert_eq!(result, "a=1 origin b=2");
+    }
+
+    #[tokio::test]
+    async fn rt_should_return_binary_response_body() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.eval(
+            "addEventListener('fetch', (event) => {
+                const bytes = new Uint8Array([0, 1, 2, 255]);
+                event.respondWith(new Response(bytes.subarray(1)));
+            });",
+        )
+        .unwrap();
+
+        let mut fetch =
+            RuntimeFetchMessage::new(JsRequest::new(String::from("/"), String::from("GET")));
+
+        rt.send_message(&mut fetch).unwrap();
+        rt.run_event_loop().await.unwrap();
+
+        let response = fetch.get_response().await.unwrap();
+
+        assert_eq!(response.body, vec![1, 2, 255]);
+    }
+
+    #[tokio::test]
+    async fn rt_should_reject_unsupported_response_body() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.eval(
+            "addEventListener('fetch', (event) => {
+                event.respondWith(42);
+            });",
+        )
+        .unwrap();
+
+        let mut fetch =
+            RuntimeFetchMessage::new(JsRequest::new(String::from("/"), String::from("GET")));
+
+        rt.send_message(&mut fetch).unwrap();
+        rt.run_event_loop().await.unwrap();
+
+        let response = fetch.get_response().await.unwrap();
+
+        assert_eq!(response.status, 500);
+        assert!(response.body.starts_with(b"TypeError"));
+    }
+
+    #[test]
+    fn rt_should_parse_urls() {
+        let mut rt = JsRuntime::create_init(None);
+
+        let result = rt
+            .eval(
+                "const url = new URL('../b?x=1&y=a+b#top', 'https://user@example.com:8080/a/c');
+                url.searchParams.append('z', 'é');
+                [url.pathname, url.port, url.searchParams.get('y'), url.search, url.origin].join(' ')",
+            )
+            .unwrap();
+
+        assert_eq!(
+            result,
+            "/b 8080 a b ?x=1&y=a+b&z=%C3%A9 https://example.com:8080"
+        );
+
+        let result = rt
+            .eval(
+                "url.search = '?q=2';
+                url.hostname = 'example.org';
+                [url.searchParams.get('q'), url.searchParams.has('x'), JSON.stringify({ url })].join(' ')",
+            )
+            .unwrap();
+
+        assert_eq!(
+            result,
+            "2 false {\"url\":\"https://user@example.org:8080/b?q=2#top\"}"
+        );
+
+        let result = rt
+            .eval("[URL.canParse('/relative'), URL.canParse('/relative', 'http://a')].join(' ')")
+            .unwrap();
+
+        assert_eq!(result, "false true");
+
+        let result = rt
+            .eval("try { new URL('nope'); } catch (err) { err.name }")
+            .unwrap();
+
+        assert_eq!(result, "TypeError");
+    }
+
+    #[tokio::test]
+    async fn rt_should_build_responses() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.eval(
+            "globalThis.results = [];
+            const json = Response.json({ a: 1 }, { status: 201 });
+            results.push(json.status, json.ok, json.headers.get('content-type'));
+            const redirect = Response.redirect('https://example.com/', 301);
+            results.push(redirect.headers.get('location'), Response.error().type);
+            try { new Response(null, { status: 99 }); } catch (err) { results.push(err.name); }
+            const clone = json.clone();
+            json.json()
+                .then((body) => results.push(body.a, json.bodyUsed, clone.bodyUsed))
+                .then(() => clone.text())
+                .then((text) => results.push(text));",
+        )
+        .unwrap();
+
+        rt.run_event_loop().await.unwrap();
+
+        let result = rt.eval("globalThis.results.join(' ')").unwrap();
+        assert_eq!(
+            result,
+            "201 true application/json https://example.com/ error RangeError 1 true false {\"a\":1}"
+        );
+    }
+
+    #[tokio::test]
+    async fn rt_should_send_blob_response_body() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.eval(
+            "addEventListener('fetch', (event) => {
+                const blob = new Blob(['<p>', 'hi', '</p>'], { type: 'text/html' });
+                event.respondWith(new Response(blob));
+            });",
+        )
+        .unwrap();
+
+        let mut fetch =
+            RuntimeFetchMessage::new(JsRequest::new(String::from("/"), String::from("GET")));
+
+        rt.send_message(&mut fetch).unwrap();
+        rt.run_event_loop().await.unwrap();
+
+        let response = fetch.get_response().await.unwrap();
+
+        assert_eq!(response.body, b"<p>hi</p>");
+        assert_eq!(
+            response.headers,
+            vec![(String::from("content-type"), String::from("text/html"))]
+        );
+    }
+
+    #[tokio::test]
+    async fn rt_should_run_wait_until_after_response() {
+        let sink = MemorySink::new();
+
+        let mut rt = JsRuntime::create_init(None);
+        rt.set_console_sink(sink.clone());
+
+        rt.eval(
+            "globalThis.done = false;
+            addEventListener('fetch', (event) => {
+                event.waitUntil(new Promise((resolve) => setTimeout(resolve, 10)).then(() => done = true));
+                event.waitUntil(Promise.reject(new Error('cache write failed')));
+                event.respondWith(new Response('ok'));
+            });",
+        )
+        .unwrap();
+
+        let mut fetch =
+            RuntimeFetchMessage::new(JsRequest::new(String::from("/"), String::from("GET")));
+
+        rt.send_message(&mut fetch).unwrap();
+        rt.run_event_loop().await.unwrap();
+
+        assert!(fetch.get_response().await.is_some());
+        assert_eq!(rt.eval("globalThis.done").unwrap(), "true");
+        assert!(rt.take_error_reports().is_empty());
+
+        let records = sink.records();
+        let errors: Vec<_> = records
+            .iter()
+            .filter(|record| record.level == LogLevel::Error)
+            .collect();
+
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].args[1].contains("cache write failed"));
+    }
+
+    #[tokio::test]
+    async fn rt_should_cancel_pending_work() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.eval(
+            "globalThis.fired = [];
+            setTimeout(() => fired.push('script'), 10);",
+        )
+        .unwrap();
+
+        rt.set_request_id(Some(String::from("42")));
+        rt.eval("setTimeout(() => fired.push('request'), 10);")
+            .unwrap();
+
+        // Only work started while handling the request is dropped
+        rt.cancel_pending();
+        rt.set_request_id(None);
+        rt.run_event_loop().await.unwrap();
+
+        assert_eq!(rt.eval("fired.join()").unwrap(), "script");
+    }
+
+    #[tokio::test]
+    async fn rt_should_settle_fetch_event_with_wait_until() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.eval(
+            "globalThis.done = false;
+            setInterval(() => {}, 5);
+            addEventListener('fetch', (event) => {
+                event.waitUntil(new Promise((resolve) => setTimeout(resolve, 20)).then(() => done = true));
+                event.respondWith(new Response('ok'));
+            });",
+        )
+        .unwrap();
+
+        let mut fetch =
+            RuntimeFetchMessage::new(JsRequest::new(String::from("/"), String::from("GET")));
+
+        rt.send_message(&mut fetch).unwrap();
+
+        // The interval keeps the event loop running
+        {
+            let event_loop = rt.run_event_loop();
+
+            let settled = tokio::time::timeout(Duration::from_secs(1), async {
+                tokio::select! {
+                    _ = event_loop => panic!("event loop should not end"),
+                    _ = fetch.settled() => {}
+                }
+            });
+
+            settled.await.unwrap();
+        }
+
+        assert!(fetch.get_response().await.is_some());
+        assert_eq!(rt.eval("globalThis.done").unwrap(), "true");
+    }
+
+    #[tokio::test]
+    async fn rt_should_report_unhandled_fetch_event() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.eval(
+            "addEventListener('fetch', (event) => {
+                setTimeout(() => {
+                    try {
+                        event.respondWith(new Response('late'));
+                    } catch (err) {
+                        globalThis.late = err.message;
+                    }
+                });
+            });",
+        )
+        .unwrap();
+
+        let mut fetch =
+            RuntimeFetchMessage::new(JsRequest::new(String::from("/"), String::from("GET")));
+
+        rt.send_message(&mut fetch).unwrap();
+
+        // Known as soon as the listeners return
+        assert!(fetch.try_get_response().is_none());
+        assert_eq!(fetch.no_response(), Some(NoResponse::NotHandled));
+
+        rt.run_event_loop().await.unwrap();
+
+        assert!(fetch.get_response().await.is_none());
+        assert_eq!(fetch.no_response(), Some(NoResponse::NotHandled));
+        assert!(rt
+            .eval("globalThis.late")
+            .unwrap()
+            .contains("must be called synchronously"));
+    }
+
+    #[tokio::test]
+    async fn rt_should_report_unsettled_response() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.eval(
+            "addEventListener('fetch', (event) => {
+                event.respondWith(new Promise(() => {}));
+            });",
+        )
+        .unwrap();
+
+        let mut fetch =
+            RuntimeFetchMessage::new(JsRequest::new(String::from("/"), String::from("GET")));
+
+        rt.send_message(&mut fetch).unwrap();
+        rt.run_event_loop().await.unwrap();
+
+        assert!(fetch.try_get_response().is_none());
+        assert_eq!(fetch.no_response(), Some(NoResponse::Unsettled));
+    }
+
+    #[tokio::test]
+    async fn rt_should_pass_through_on_exception() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.eval(
+            "addEventListener('fetch', (event) => {
+                event.passThroughOnException();
+
+                if (event.request.method === 'POST') {
+                    throw new Error('sync failure');
+                }
+
+                event.respondWith(Promise.reject(new Error('async failure')));
+            });",
+        )
+        .unwrap();
+
+        for method in ["POST", "GET"] {
+            let mut fetch =
+                RuntimeFetchMessage::new(JsRequest::new(String::from("/"), String::from(method)));
+
+            rt.send_message(&mut fetch).unwrap();
+            rt.run_event_loop().await.unwrap();
+
+            assert!(fetch.get_response().await.is_none());
+            assert_eq!(fetch.no_response(), Some(NoResponse::PassThrough));
+        }
+    }
+
+    #[tokio::test]
+    async fn rt_should_stream_response_body() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.eval(
+            "addEventListener('fetch', (event) => {
+                let count = 0;
+                const body = new ReadableStream({
+                    async pull(controller) {
+                        await new Promise((resolve) => setTimeout(resolve, 1));
+                        if (++count > 3) return controller.close();
+                        controller.enqueue(`chunk${count};`);
+                    },
+                });
+                event.respondWith(new Response(body));
+            });",
+        )
+        .unwrap();
+
+        let mut fetch =
+            RuntimeFetchMessage::new(JsRequest::new(String::from("/"), String::from("GET")));
+
+        rt.send_message(&mut fetch).unwrap();
+
+        let response = async {
+            let mut response = fetch.get_response().await.unwrap();
+            response.collect_body().await.unwrap();
+            response
+        };
+
+        let (result, response) = tokio::join!(rt.run_event_loop(), response);
+
+        result.unwrap();
+        assert_eq!(response.body, b"chunk1;chunk2;chunk3;");
+    }
+
+    #[tokio::test]
+    async fn rt_should_cancel_stream_when_receiver_is_dropped() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.eval(
+            "globalThis.canceled = false;
+            addEventListener('fetch', (event) => {
+                const body = new ReadableStream({
+                    pull(controller) { controller.enqueue('chunk'); },
+                    cancel() { canceled = true; },
+                });
+                event.respondWith(new Response(body));
+            });",
+        )
+        .unwrap();
+
+        let mut fetch =
+            RuntimeFetchMessage::new(JsRequest::new(String::from("/"), String::from("GET")));
+
+        rt.send_message(&mut fetch).unwrap();
+
+        // Client reads a single chunk, then disconnects
+        let client = async {
+            let mut response = fetch.get_response().await.unwrap();
+            let mut stream = response.stream.take().unwrap();
+            stream.recv().await.unwrap().unwrap()
+        };
+
+        let (result, chunk) = tokio::join!(rt.run_event_loop(), client);
+
+        result.unwrap();
+        assert_eq!(chunk, b"chunk");
+        assert_eq!(rt.eval("canceled").unwrap(), "true");
+    }
+
+    fn echo_client() -> MockHttpClient {
+        MockHttpClient::new(|request| match request.url.as_str() {
+            "https://example.com/old" => Ok(FetchResponse {
+                status: 301,
+                headers: vec![(String::from("location"), String::from("/new"))],
+                ..Default::default()
+            }),
+            _ => Ok(FetchResponse {
+                status: 201,
+                status_text: String::from("Created"),
+                headers: vec![(String::from("x-method"), request.method.clone())],
+                body: request.body.clone().unwrap_or_default(),
+                ..Default::default()
+            }),
+        })
+    }
+
+    #[tokio::test]
+    async fn rt_should_fetch_with_http_client() {
+        let mut rt = JsRuntimeBuilder::new()
+            .http_client(echo_client())
+            .build()
+            .unwrap();
+
+        rt.eval(
+            "fetch('https://example.com/echo', { method: 'post', body: 'hello' })
+                .then((res) => globalThis.result = [
+                    res.status,
+                    res.statusText,
+                    res.headers.get('x-method'),
+                    res.body.byteLength,
+                ].join(' '))",
+        )
+        .unwrap();
+
+        rt.run_event_loop().await.unwrap();
+
+        let result = rt.eval("globalThis.result").unwrap();
+        assert_eq!(result, "201 Created POST 5");
+    }
+
+    #[tokio::test]
+    async fn rt_should_fetch_with_redirect_modes() {
+        let mut rt = JsRuntime::create_init(None);
+        rt.set_http_client(echo_client());
+
+        rt.eval(
+            "globalThis.results = [];
+            const url = 'https://example.com/old';
+            fetch(url).then((res) => results.push(`${res.redirected} ${res.url}`))
+                .then(() => fetch(url, { redirect: 'manual' }))
+                .then((res) => results.push(`${res.type} ${res.status} ${res.headers.get('location')} ${res.clone().type}`))
+                .then(() => fetch(url, { redirect: 'error' }))
+                .catch((err) => results.push(err.name));",
+        )
+        .unwrap();
+
+        rt.run_event_loop().await.unwrap();
+
+        let result = rt.eval("globalThis.results.join(', ')").unwrap();
+        assert_eq!(
+            result,
+            "true https://example.com/new, opaqueredirect 0 null opaqueredirect, TypeError"
+        );
+    }
+
+    #[tokio::test]
+    async fn rt_should_reject_fetch_on_timeout() {
+        let client = echo_client().with_delay(Duration::from_millis(100));
+        let mut rt = JsRuntimeBuilder::new().http_client(client).build().unwrap();
+
+        rt.eval(
+            "fetch('https://example.com/slow', { timeout: 10 })
+                .catch((err) => globalThis.error = err.message)",
+        )
+        .unwrap();
+
+        rt.run_event_loop().await.unwrap();
+
+        let result = rt.eval("globalThis.error").unwrap();
+        assert_eq!(result, "fetch failed: Request timed out");
+    }
+
+    #[test]
+    fn rt_should_encode_and_decode_base64() {
+        let mut rt = JsRuntime::create_init(None);
+
+        let result = rt
+            .eval(
+                "const errors = [];
+                for (const call of [() => atob('YQ='), () => btoa('€'), () => atob('a b c d e')]) {
+                    try {
+                        call();
+                    } catch (err) {
+                        errors.push(`${err.name} ${err.code} ${err instanceof DOMException}`);
+                    }
+                }
+
+                [
+                    btoa('hello\\xff'),
+                    atob(' aGVs bG8\\n'),
+                    atob('YR=='),
+                    atob(btoa('\\x00\\x80\\xff')).length,
+                    atob(null).length,
+                    errors.join(', '),
+                ].join(' ')",
+            )
+            .unwrap();
+
+        assert_eq!(
+            result,
+            "aGVsbG// hello a 3 3 InvalidCharacterError 5 true, InvalidCharacterError 5 true, InvalidCharacterError 5 true"
+        );
+        assert!(rt.eval("atob()").is_err());
+    }
+
+    #[test]
+    fn rt_should_encode_and_decode_text() {
+        let mut rt = JsRuntime::create_init(None);
+
+        let result = rt
+            .eval(
+                "const encoder = new TextEncoder();
+                const bytes = encoder.encode('h€\\ud800');
+                const target = new Uint8Array(5);
+                const { read, written } = encoder.encodeInto('ab€c', target);
+
+                const decoder = new TextDecoder();
+                const streamed = [
+                    decoder.decode(new Uint8Array([0xef, 0xbb, 0xbf, 0x61, 0xe2]), { stream: true }),
+                    decoder.decode(new Uint8Array([0x82, 0xac])),
+                ];
+
+                let fatal;
+                try {
+                    new TextDecoder('utf8', { fatal: true }).decode(new Uint8Array([0xff]));
+                } catch (err) {
+                    fatal = err.name;
+                }
+
+                [
+                    bytes.join(),
+                    `${read} ${written}`,
+                    streamed.join('|'),
+                    new TextDecoder('utf-8', { ignoreBOM: true }).decode(new Uint8Array([0xef, 0xbb, 0xbf])).length,
+                    new TextDecoder('utf-16').decode(new Uint8Array([0xff, 0xfe, 0x61, 0x00])),
+                    new TextDecoder('latin1').decode(new Uint8Array([0x80, 0xe9])),
+                    new TextDecoder(' Latin1 ').encoding,
+                    fatal,
+                ].join(' ')",
+            )
+            .unwrap();
+
+        assert_eq!(
+            result,
+            "104,226,130,172,239,191,189 3 5 a|€ 1 a €é windows-1252 TypeError"
+        );
+        assert!(rt.eval("new TextDecoder('utf-7')").is_err());
+    }
+
+    #[tokio::test]
+    async fn rt_should_abort_fetch() {
+        let client = echo_client().with_delay(Duration::from_secs(10));
+        let mut rt = JsRuntimeBuilder::new().http_client(client).build().unwrap();
+
+        rt.eval(
+            "const controller = new AbortController();
+            fetch('https://example.com/slow', { signal: controller.signal })
+                .catch((err) => globalThis.error = `${err.name} ${err.code}`);
+            setTimeout(() => controller.abort(), 10);
+            fetch('https://example.com/slow', { signal: AbortSignal.abort('early') })
+                .catch((err) => globalThis.early = err);",
+        )
+        .unwrap();
+
+        let start = std::time::Instant::now();
+        rt.run_event_loop().await.unwrap();
+
+        // The pending request is dropped with the op
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(rt.eval("globalThis.error").unwrap(), "AbortError 20");
+        assert_eq!(rt.eval("globalThis.early").unwrap(), "early");
+    }
+
+    #[tokio::test]
+    async fn rt_should_combine_abort_signals() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.eval(
+            "const controller = new AbortController();
+            const any = AbortSignal.any([controller.signal, AbortSignal.timeout(10)]);
+            const events = [];
+            any.addEventListener('abort', () => events.push(any.reason.name));
+            AbortSignal.timeout(60000).onabort = () => events.push('late');
+            setTimeout(() => controller.abort(), 50);",
+        )
+        .unwrap();
+
+        // Timeouts of signals do not keep the event loop running
+        let start = std::time::Instant::now();
+        rt.run_event_loop().await.unwrap();
+
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(
+            rt.eval("[events.join(), controller.signal.reason.name].join(' ')")
+                .unwrap(),
+            "TimeoutError AbortError"
+        );
+        assert!(rt.eval("new AbortSignal()").is_err());
+    }
+
+    #[test]
+    fn rt_should_abort_dependents_of_dependent_signals() {
+        let mut rt = JsRuntime::create_init(None);
+
+        let result = rt.eval(
+            "const controller = new AbortController();
+            const request = new Request('http://localhost/', { signal: controller.signal });
+            const any = AbortSignal.any([request.signal]);
+            const events = [];
+            request.signal.onabort = () => events.push('request');
+            any.addEventListener('abort', () => events.push('any'));
+            for (let i = 0; i < 100; i++) AbortSignal.any([controller.signal]);
+            controller.abort();
+            events.join()",
+        );
+
+        assert_eq!(result.unwrap(), "request,any");
+    }
+
+    #[tokio::test]
+    async fn rt_should_abort_request_signal_when_client_is_gone() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.eval(
+            "addEventListener('fetch', (event) => {
+                const signal = event.request.signal;
+
+                event.respondWith(new Promise((resolve) => {
+                    signal.addEventListener('abort', () => resolve(new Response(signal.reason.name)));
+                }));
+            });",
+        )
+        .unwrap();
+
+        let mut fetch =
+            RuntimeFetchMessage::new(JsRequest::new(String::from("/"), String::from("GET")));
+
+        rt.send_message(&mut fetch).unwrap();
+        rt.send_message(&mut RuntimeFetchMessage::abort_message())
+            .unwrap();
+        rt.run_event_loop().await.unwrap();
+
+        let response = fetch.get_response().await.unwrap();
+
+        assert_eq!(response.body, b"AbortError");
     }
 
     #[test]
@@ -55,8 +1283,86 @@ mod tests {
         let result = rt.eval("import('moduleName')").unwrap();
 
         assert_eq!(result, String::from("[object Promise]"));
+    }
+
+    #[tokio::test]
+    async fn rt_should_reject_dynamic_import_of_unknown_module() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.eval("import('moduleName').catch((err) => globalThis.importError = err.name)")
+            .unwrap();
+        rt.run_event_loop().await.unwrap();
+
+        let result = rt.eval("globalThis.importError").unwrap();
+
+        assert_eq!(result, String::from("TypeError"));
+    }
+
+    #[tokio::test]
+    async fn rt_should_resolve_dynamic_import() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.eval("import('./scripts/modules/lib.mjs').then((lib) => globalThis.libName = lib.name)")
+            .unwrap();
+        rt.run_event_loop().await.unwrap();
+
+        let result = rt.eval("globalThis.libName").unwrap();
+
+        assert_eq!(result, String::from("lib"));
+    }
+
+    #[tokio::test]
+    async fn rt_should_evaluate_dynamic_import_asynchronously() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.eval(
+            "globalThis.order = [];
+            import('./scripts/modules/lib.mjs').then(() => order.push('imported'));
+            Promise.resolve().then(() => order.push('microtask'));
+            order.push(typeof globalThis.libLoads);",
+        )
+        .unwrap();
+        rt.run_event_loop().await.unwrap();
 
-        // TODO: promise should have been rejected
+        let result = rt.eval("order.join()").unwrap();
+
+        assert_eq!(result, String::from("undefined,microtask,imported"));
+    }
+
+    #[tokio::test]
+    async fn rt_should_use_custom_module_loader() {
+        struct MemoryLoader;
+
+        impl ModuleLoader for MemoryLoader {
+            fn resolve(
+                &self,
+                specifier: &str,
+                _referrer: Option<&str>,
+            ) -> Result<String, EvalError> {
+                Ok(specifier.to_string())
+            }
+
+            fn load(&self, name: &str) -> Result<String, EvalError> {
+                match name {
+                    "virtual:answer" => Ok(String::from("export default 42;")),
+                    _ => Err(EvalError::ResolutionError(format!(
+                        "Unknown module \"{}\"",
+                        name
+                    ))),
+                }
+            }
+        }
+
+        let mut rt = JsRuntime::create_init(None);
+        rt.set_module_loader(MemoryLoader);
+
+        rt.eval("import('virtual:answer').then((m) => globalThis.answer = m.default)")
+            .unwrap();
+        rt.run_event_loop().await.unwrap();
+
+        let result = rt.eval("globalThis.answer").unwrap();
+
+        assert_eq!(result, String::from("42"));
     }
 
     #[test]
@@ -65,8 +1371,7 @@ mod tests {
 
         let result = rt.eval("typeof import");
 
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), EvalError::CompileError);
+        assert!(matches!(result, Err(EvalError::CompileError(_))));
     }
 
     #[test]
@@ -113,4 +1418,32 @@ mod tests {
 
         assert_eq!(result, String::from("true"));
     }
+
+    #[tokio::test]
+    async fn rt_should_load_module_graph() {
+        let mut rt = JsRuntime::create_init(None);
+
+        rt.load_main_module("scripts/modules/main.mjs")
+            .await
+            .unwrap();
+
+        let result = rt.eval("globalThis.moduleResult").unwrap();
+
+        assert_eq!(result, String::from("Hello from lib (loaded 1 times)"));
+    }
+
+    #[tokio::test]
+    async fn rt_should_fail_on_missing_module() {
+        let mut rt = JsRuntime::create_init(None);
+
+        let result = rt.load_main_module("scripts/modules/broken.mjs").await;
+
+        match result {
+            Err(EvalError::ResolutionError(message)) => {
+                assert!(message.contains("missing.mjs"));
+                assert!(message.contains("broken.mjs"));
+            }
+            _ => panic!("Expected resolution error, got {:?}", result.err()),
+        }
+    }
 }
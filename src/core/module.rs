@@ -0,0 +1,312 @@
+use v8::Global;
+use v8::HandleScope;
+use v8::Local;
+use v8::Module;
+
+use std::collections::HashMap;
+use std::num::NonZeroI32;
+use std::path::Path;
+
+use super::ops::spawn_op;
+use super::ops::OpResult;
+use super::EvalError;
+use super::JsError;
+use super::JsStateRef;
+
+/// Resolves and fetches the source of ES modules
+pub trait ModuleLoader {
+    /// Resolve a specifier into a module name, `referrer` is the name of the
+    /// importing module or `None` for entrypoints and classic scripts
+    fn resolve(&self, specifier: &str, referrer: Option<&str>) -> Result<String, EvalError>;
+
+    /// Fetch the source code of a resolved module
+    fn load(&self, name: &str) -> Result<String, EvalError>;
+}
+
+/// Default loader, resolves relative specifiers on the filesystem
+pub struct FsModuleLoader;
+
+impl ModuleLoader for FsModuleLoader {
+    fn resolve(&self, specifier: &str, referrer: Option<&str>) -> Result<String, EvalError> {
+        let path = match referrer {
+            Some(referrer) => {
+                let is_relative = specifier.starts_with("./")
+                    || specifier.starts_with("../")
+                    || specifier.starts_with('/');
+
+                if !is_relative {
+                    return Err(EvalError::ResolutionError(format!(
+                        "Unsupported module specifier \"{}\" imported from \"{}\"",
+                        specifier, referrer
+                    )));
+                }
+
+                let base = Path::new(referrer).parent().unwrap_or(Path::new("."));
+
+                base.join(specifier)
+            }
+            None => Path::new(specifier).to_path_buf(),
+        };
+
+        match path.canonicalize() {
+            Ok(path) => Ok(path.to_string_lossy().to_string()),
+            Err(error) => Err(EvalError::ResolutionError(match referrer {
+                Some(referrer) => format!(
+                    "Cannot resolve module \"{}\" imported from \"{}\": {}",
+                    specifier, referrer, error
+                ),
+                None => format!("Cannot resolve module \"{}\": {}", specifier, error),
+            })),
+        }
+    }
+
+    fn load(&self, name: &str) -> Result<String, EvalError> {
+        std::fs::read_to_string(name).map_err(|error| {
+            EvalError::ResolutionError(format!("Cannot read module \"{}\": {}", name, error))
+        })
+    }
+}
+
+/// Modules compiled in a runtime, keyed by their resolved name
+#[derive(Default)]
+pub struct ModuleMap {
+    modules: HashMap<String, Global<Module>>,
+    names: HashMap<NonZeroI32, String>,
+}
+
+impl ModuleMap {
+    pub fn get(&self, name: &str) -> Option<&Global<Module>> {
+        self.modules.get(name)
+    }
+
+    /// Name of a compiled module, looked up by its identity hash
+    pub fn name_of(&self, hash: NonZeroI32) -> Option<&String> {
+        self.names.get(&hash)
+    }
+
+    fn insert(&mut self, name: String, hash: NonZeroI32, module: Global<Module>) {
+        self.names.insert(hash, name.clone());
+        self.modules.insert(name, module);
+    }
+}
+
+fn module_origin<'s>(scope: &mut HandleScope<'s>, name: &str) -> v8::ScriptOrigin<'s> {
+    let name = v8::String::new(scope, name).unwrap();
+    let source_map_url = v8::String::empty(scope);
+
+    v8::ScriptOrigin::new(
+        scope,
+        name.into(),
+        0,
+        0,
+        false,
+        0,
+        source_map_url.into(),
+        false,
+        false,
+        true,
+    )
+}
+
+/// Compile a module and all its static dependencies, each module only once
+pub(crate) fn load_module<'s>(
+    scope: &mut v8::TryCatch<HandleScope<'s>>,
+    name: &str,
+) -> Result<Local<'s, Module>, EvalError> {
+    let state = scope
+        .get_slot::<JsStateRef>()
+        .expect("No state found")
+        .clone();
+
+    if let Some(module) = state.borrow().modules.get(name) {
+        return Ok(Local::new(scope, module));
+    }
+
+    let loader = state.borrow().loader.clone();
+    let code = loader.load(name)?;
+
+    let origin = module_origin(scope, name);
+    let code = v8::String::new(scope, &code).ok_or(EvalError::ConversionError)?;
+    let source = v8::script_compiler::Source::new(code, Some(&origin));
+
+    let module = match v8::script_compiler::compile_module(scope, source) {
+        Some(module) => module,
+        None => return Err(EvalError::CompileError(JsError::from_try_catch(scope))),
+    };
+
+    // Register before walking dependencies so that cycles resolve to this instance
+    {
+        let global = Global::new(scope, module);
+        let hash = module.get_identity_hash();
+        state
+            .borrow_mut()
+            .modules
+            .insert(name.to_string(), hash, global);
+    }
+
+    let requests = module.get_module_requests();
+
+    for i in 0..requests.length() {
+        let request = requests.get(scope, i).unwrap();
+        let request: Local<v8::ModuleRequest> = request.try_into().unwrap();
+        let specifier = request.get_specifier().to_rust_string_lossy(scope);
+
+        let dependency = loader.resolve(&specifier, Some(name))?;
+
+        load_module(scope, &dependency)?;
+    }
+
+    Ok(module)
+}
+
+/// Load, instantiate and evaluate a module, returns the evaluation result
+pub(crate) fn evaluate_module<'s>(
+    scope: &mut v8::TryCatch<HandleScope<'s>>,
+    name: &str,
+) -> Result<(Local<'s, Module>, Local<'s, v8::Value>), EvalError> {
+    let module = load_module(scope, name)?;
+
+    if module
+        .instantiate_module(scope, resolve_module_callback)
+        .is_none()
+    {
+        return Err(EvalError::CompileError(JsError::from_try_catch(scope)));
+    }
+
+    let result = match module.evaluate(scope) {
+        Some(result) => result,
+        None => return Err(EvalError::RuntimeError(JsError::from_try_catch(scope))),
+    };
+
+    Ok((module, result))
+}
+
+/// Resolve static imports to modules previously compiled by `load_module`
+fn resolve_module_callback<'a>(
+    context: Local<'a, v8::Context>,
+    specifier: Local<'a, v8::String>,
+    _import_assertions: Local<'a, v8::FixedArray>,
+    referrer: Local<'a, Module>,
+) -> Option<Local<'a, Module>> {
+    let scope = &mut unsafe { v8::CallbackScope::new(context) };
+
+    let specifier = specifier.to_rust_string_lossy(scope);
+
+    let state = scope
+        .get_slot::<JsStateRef>()
+        .expect("No state found")
+        .clone();
+    let state = state.borrow();
+
+    let module = state
+        .modules
+        .name_of(referrer.get_identity_hash())
+        .and_then(|referrer| state.loader.resolve(&specifier, Some(referrer)).ok())
+        .and_then(|name| state.modules.get(&name));
+
+    match module {
+        Some(module) => Some(Local::new(scope, module)),
+        None => {
+            drop(state);
+
+            let message = format!("Cannot resolve module \"{}\"", specifier);
+            crate::utils::throw_type_error(scope, &message);
+            None
+        }
+    }
+}
+
+/// Module requested by `import()`, resolved, loaded and evaluated once the
+/// event loop gets to it rather than from the host callback
+struct DynamicImport {
+    specifier: String,
+    referrer: Option<String>,
+}
+
+impl OpResult for DynamicImport {
+    /// Module namespace, or a promise of it with top-level await
+    fn into_value<'s>(
+        self: Box<Self>,
+        scope: &mut HandleScope<'s>,
+    ) -> Result<Local<'s, v8::Value>, Local<'s, v8::Value>> {
+        let state = scope
+            .get_slot::<JsStateRef>()
+            .expect("No state found")
+            .clone();
+        let loader = state.borrow().loader.clone();
+
+        let tc = &mut v8::TryCatch::new(scope);
+
+        let evaluated = loader
+            .resolve(&self.specifier, self.referrer.as_deref())
+            .and_then(|name| evaluate_module(tc, &name));
+
+        let (module, result) = match evaluated {
+            Ok(evaluated) => evaluated,
+            // Terminated, e.g. on time limit
+            Err(_) if !tc.can_continue() => return Err(v8::undefined(tc).into()),
+            Err(error) => {
+                let exception = match tc.exception() {
+                    Some(exception) => exception,
+                    None => {
+                        let message = match error {
+                            EvalError::ResolutionError(message) => message,
+                            _ => format!("Cannot resolve module \"{}\"", self.specifier),
+                        };
+                        let message = v8::String::new(tc, &message).unwrap();
+                        v8::Exception::type_error(tc, message)
+                    }
+                };
+
+                tc.reset();
+                return Err(exception);
+            }
+        };
+
+        let namespace = module.get_module_namespace();
+
+        match Local::<v8::Promise>::try_from(result) {
+            // Top-level await, settle with the namespace once evaluated
+            Ok(evaluation) => {
+                let on_fulfilled = v8::Function::builder(
+                    |_scope: &mut HandleScope,
+                     args: v8::FunctionCallbackArguments,
+                     mut rv: v8::ReturnValue| {
+                        rv.set(args.data());
+                    },
+                )
+                .data(namespace)
+                .build(tc)
+                .unwrap();
+
+                Ok(evaluation.then(tc, on_fulfilled).unwrap().into())
+            }
+            Err(_) => Ok(namespace),
+        }
+    }
+}
+
+/// Host callback for `import()`, the returned promise is settled with the
+/// module namespace by the event loop once the imported graph is evaluated
+pub(crate) fn dynamic_import_callback<'s>(
+    scope: &mut HandleScope<'s>,
+    _host_defined_options: Local<'s, v8::Data>,
+    resource_name: Local<'s, v8::Value>,
+    specifier: Local<'s, v8::String>,
+    _import_assertions: Local<'s, v8::FixedArray>,
+) -> Option<Local<'s, v8::Promise>> {
+    let specifier = specifier.to_rust_string_lossy(scope);
+
+    // Without a resource name, the specifier is resolved like an entrypoint
+    let referrer = match resource_name.is_string() {
+        true => Some(resource_name.to_rust_string_lossy(scope)),
+        false => None,
+    };
+
+    let import = DynamicImport {
+        specifier,
+        referrer,
+    };
+
+    Some(spawn_op(scope, async move { import }))
+}
@@ -0,0 +1,199 @@
+use v8::Global;
+use v8::HandleScope;
+use v8::Local;
+use v8::Value;
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+
+use futures::future::AbortHandle;
+use futures::future::Abortable;
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
+
+use super::JsStateRef;
+
+/// Outcome of an async op, converted to a JS value once the op completes
+pub(crate) trait OpResult {
+    /// Value the promise is resolved with, or rejected with on `Err`
+    fn into_value<'s>(
+        self: Box<Self>,
+        scope: &mut HandleScope<'s>,
+    ) -> Result<Local<'s, Value>, Local<'s, Value>>;
+}
+
+/// Resolves with undefined, or rejects with a TypeError
+impl OpResult for Result<(), String> {
+    fn into_value<'s>(
+        self: Box<Self>,
+        scope: &mut HandleScope<'s>,
+    ) -> Result<Local<'s, Value>, Local<'s, Value>> {
+        match *self {
+            Ok(()) => Ok(v8::undefined(scope).into()),
+            Err(message) => {
+                let message = v8::String::new(scope, &message).unwrap();
+                Err(v8::Exception::type_error(scope, message))
+            }
+        }
+    }
+}
+
+type CompletedOp = (Global<v8::PromiseResolver>, Box<dyn OpResult>);
+
+/// Canceled ops complete with `None`
+type OpFuture = Pin<Box<dyn Future<Output = (u32, Option<CompletedOp>)>>>;
+
+/// Op still running
+struct RunningOp {
+    handle: AbortHandle,
+    /// Request being handled when the op was started
+    request_id: Option<String>,
+}
+
+/// Host futures whose promises are settled by the event loop
+#[derive(Default)]
+pub struct PendingOps {
+    futures: FuturesUnordered<OpFuture>,
+    next_id: u32,
+    running: HashMap<u32, RunningOp>,
+}
+
+impl PendingOps {
+    pub fn is_empty(&self) -> bool {
+        self.futures.is_empty()
+    }
+
+    /// Drop the ops started while handling `request_id`, their promises
+    /// never settle
+    pub(crate) fn cancel_request(&mut self, request_id: &str) {
+        self.running.retain(|_, op| {
+            let canceled = op.request_id.as_deref() == Some(request_id);
+
+            if canceled {
+                op.handle.abort();
+            }
+
+            !canceled
+        });
+    }
+
+    /// Drop the op `id` if it is still running, its promise never settles
+    pub(crate) fn cancel(&mut self, id: u32) {
+        if let Some(op) = self.running.remove(&id) {
+            op.handle.abort();
+        }
+    }
+
+    fn push<F, R>(
+        &mut self,
+        resolver: Global<v8::PromiseResolver>,
+        future: F,
+        request_id: Option<String>,
+    ) -> u32
+    where
+        F: Future<Output = R> + 'static,
+        R: OpResult + 'static,
+    {
+        self.next_id = self.next_id.wrapping_add(1);
+        let id = self.next_id;
+
+        let (handle, registration) = AbortHandle::new_pair();
+        let future = Abortable::new(future, registration);
+
+        self.futures.push(Box::pin(async move {
+            let result = future.await.ok().map(|result| {
+                let result: Box<dyn OpResult> = Box::new(result);
+                (resolver, result)
+            });
+
+            (id, result)
+        }));
+
+        self.running.insert(id, RunningOp { handle, request_id });
+
+        id
+    }
+
+    /// Next completed op, pending forever when there is none
+    pub(crate) fn poll_next(&mut self, cx: &mut Context) -> Poll<CompletedOp> {
+        loop {
+            match self.futures.poll_next_unpin(cx) {
+                Poll::Ready(Some((id, op))) => {
+                    self.running.remove(&id);
+
+                    if let Some(op) = op {
+                        return Poll::Ready(op);
+                    }
+                }
+                Poll::Ready(None) | Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// Run `future` on the event loop, the returned promise is settled with its
+/// result
+pub(crate) fn spawn_op<'s, F, R>(scope: &mut HandleScope<'s>, future: F) -> Local<'s, v8::Promise>
+where
+    F: Future<Output = R> + 'static,
+    R: OpResult + 'static,
+{
+    let (_id, promise) = spawn_cancelable_op(scope, future);
+
+    promise
+}
+
+/// Like `spawn_op`, the returned id can be given to `__cancelOp` to drop the
+/// op before it completes
+pub(crate) fn spawn_cancelable_op<'s, F, R>(
+    scope: &mut HandleScope<'s>,
+    future: F,
+) -> (u32, Local<'s, v8::Promise>)
+where
+    F: Future<Output = R> + 'static,
+    R: OpResult + 'static,
+{
+    let resolver = v8::PromiseResolver::new(scope).unwrap();
+    let promise = resolver.get_promise(scope);
+    let resolver = Global::new(scope, resolver);
+
+    let state = scope.get_slot::<JsStateRef>().expect("No state found");
+    let mut state = state.borrow_mut();
+
+    let request_id = state.request_id.clone();
+    let id = state.ops.push(resolver, future, request_id);
+
+    (id, promise)
+}
+
+/// __cancelOp(id): drop a cancelable op, e.g. an aborted fetch
+pub(crate) fn cancel_op(
+    scope: &mut HandleScope,
+    args: v8::FunctionCallbackArguments,
+    _rv: v8::ReturnValue,
+) {
+    let id = match args.get(0).uint32_value(scope) {
+        Some(id) => id,
+        None => return,
+    };
+
+    let state = scope.get_slot::<JsStateRef>().expect("No state found");
+    state.borrow_mut().ops.cancel(id);
+}
+
+/// Settle the promise of a completed op
+pub(crate) fn resolve_op(
+    scope: &mut HandleScope,
+    resolver: Global<v8::PromiseResolver>,
+    result: Box<dyn OpResult>,
+) {
+    let resolver = Local::new(scope, resolver);
+
+    match result.into_value(scope) {
+        Ok(value) => resolver.resolve(scope, value),
+        Err(reason) => resolver.reject(scope, reason),
+    };
+}
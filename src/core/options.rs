@@ -0,0 +1,167 @@
+use std::borrow::Cow;
+use std::rc::Rc;
+use std::time::Duration;
+
+use super::ConsoleSink;
+use super::EvalError;
+use super::HeapLimits;
+use super::JsRuntime;
+use crate::fetch::HttpClient;
+
+/// Script evaluated in new contexts, unless they are restored from a snapshot
+#[derive(Debug, Clone)]
+pub struct Extension {
+    pub name: String,
+    pub source: Cow<'static, str>,
+}
+
+impl Extension {
+    pub fn new(name: impl Into<String>, source: impl Into<Cow<'static, str>>) -> Self {
+        Extension {
+            name: name.into(),
+            source: source.into(),
+        }
+    }
+}
+
+macro_rules! runtime_extension {
+    ($path:literal) => {
+        Extension::new(
+            concat!("runtime/", $path),
+            include_str!(concat!("../runtime/", $path)),
+        )
+    };
+}
+
+/// Web APIs provided by the runtime, in evaluation order
+pub fn default_extensions() -> Vec<Extension> {
+    vec![
+        runtime_extension!("init.js"),
+        runtime_extension!("dom-exception.js"),
+        runtime_extension!("base64.js"),
+        runtime_extension!("console.js"),
+        runtime_extension!("navigator.js"),
+        runtime_extension!("events.js"),
+        runtime_extension!("abort.js"),
+        runtime_extension!("encoding.js"),
+        runtime_extension!("url.js"),
+        runtime_extension!("streams.js"),
+        runtime_extension!("blob.js"),
+        runtime_extension!("fetch/headers.js"),
+        runtime_extension!("fetch/form-data.js"),
+        runtime_extension!("fetch/body.js"),
+        runtime_extension!("fetch/response.js"),
+        runtime_extension!("fetch/request.js"),
+        runtime_extension!("fetch/fetch-event.js"),
+        runtime_extension!("fetch/fetch.js"),
+    ]
+}
+
+/// Value set on the global object of new contexts
+pub enum Binding {
+    Function(v8::FunctionCallback),
+    String(String),
+    Number(f64),
+    Boolean(bool),
+}
+
+impl Binding {
+    pub fn function(callback: impl v8::MapFnTo<v8::FunctionCallback>) -> Self {
+        Binding::Function(callback.map_fn_to())
+    }
+}
+
+/// Everything needed to create a runtime, see `JsRuntimeBuilder`
+pub struct RuntimeOptions {
+    /// Snapshot created with `JsRuntime::create_snapshot`, the default
+    /// extensions are part of it and others cannot be added
+    pub snapshot: Option<Vec<u8>>,
+    pub extensions: Vec<Extension>,
+    pub heap_limits: Option<HeapLimits>,
+    /// Process-wide, only applied if V8 was not initialized yet
+    pub v8_flags: Vec<String>,
+    pub console: Option<Rc<dyn ConsoleSink>>,
+    pub time_limit: Option<Duration>,
+    /// Client used by `fetch()`, reqwest when not set
+    pub http_client: Option<Rc<dyn HttpClient>>,
+    pub bindings: Vec<(String, Binding)>,
+}
+
+impl Default for RuntimeOptions {
+    fn default() -> Self {
+        RuntimeOptions {
+            snapshot: None,
+            extensions: default_extensions(),
+            heap_limits: None,
+            v8_flags: Vec::new(),
+            console: None,
+            time_limit: None,
+            http_client: None,
+            bindings: Vec::new(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct JsRuntimeBuilder {
+    options: RuntimeOptions,
+}
+
+impl JsRuntimeBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn snapshot(mut self, snapshot: Vec<u8>) -> Self {
+        self.options.snapshot = Some(snapshot);
+        self
+    }
+
+    /// Replace the default extensions
+    pub fn extensions(mut self, extensions: Vec<Extension>) -> Self {
+        self.options.extensions = extensions;
+        self
+    }
+
+    /// Evaluate `extension` after the ones already set
+    pub fn extension(mut self, extension: Extension) -> Self {
+        self.options.extensions.push(extension);
+        self
+    }
+
+    pub fn heap_limits(mut self, heap_limits: HeapLimits) -> Self {
+        self.options.heap_limits = Some(heap_limits);
+        self
+    }
+
+    /// Add a V8 flag, e.g. `--jitless`
+    pub fn v8_flag(mut self, flag: impl Into<String>) -> Self {
+        self.options.v8_flags.push(flag.into());
+        self
+    }
+
+    pub fn console_sink<S: ConsoleSink + 'static>(mut self, sink: S) -> Self {
+        self.options.console = Some(Rc::new(sink));
+        self
+    }
+
+    pub fn time_limit(mut self, limit: Duration) -> Self {
+        self.options.time_limit = Some(limit);
+        self
+    }
+
+    pub fn http_client<C: HttpClient + 'static>(mut self, client: C) -> Self {
+        self.options.http_client = Some(Rc::new(client));
+        self
+    }
+
+    /// Set `name` on the global object
+    pub fn binding(mut self, name: impl Into<String>, binding: Binding) -> Self {
+        self.options.bindings.push((name.into(), binding));
+        self
+    }
+
+    pub fn build(self) -> Result<JsRuntime, EvalError> {
+        JsRuntime::new(self.options)
+    }
+}
@@ -0,0 +1,218 @@
+use v8::Global;
+use v8::HandleScope;
+use v8::Local;
+use v8::Value;
+
+use crate::utils;
+
+use super::JsError;
+use super::JsStateRef;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorKind {
+    UncaughtException,
+    UnhandledRejection,
+}
+
+/// Error that escaped the worker, reported to the host
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorReport {
+    pub kind: ErrorKind,
+    pub error: JsError,
+    /// Request being handled when the error occurred, if any
+    pub request_id: Option<String>,
+}
+
+impl std::fmt::Display for ErrorReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            ErrorKind::UncaughtException => write!(f, "Uncaught exception")?,
+            ErrorKind::UnhandledRejection => write!(f, "Unhandled rejection")?,
+        }
+
+        if let Some(request_id) = &self.request_id {
+            write!(f, " (request {})", request_id)?;
+        }
+
+        write!(f, ": {}", self.error)
+    }
+}
+
+/// Promises rejected without handler since the last checkpoint
+#[derive(Default)]
+pub struct Rejections {
+    pending: Vec<(Global<v8::Promise>, Global<Value>)>,
+}
+
+impl Rejections {
+    fn reject(&mut self, promise: Global<v8::Promise>, reason: Global<Value>) {
+        self.pending.push((promise, reason));
+    }
+
+    /// A handler was attached after the rejection, it is no longer unhandled
+    fn handle(&mut self, promise: Local<v8::Promise>) {
+        self.pending.retain(|(pending, _)| *pending != promise);
+    }
+
+    fn take(&mut self) -> Vec<(Global<v8::Promise>, Global<Value>)> {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+pub(crate) extern "C" fn promise_reject_callback(message: v8::PromiseRejectMessage) {
+    let scope = &mut unsafe { v8::CallbackScope::new(&message) };
+
+    let state = scope
+        .get_slot::<JsStateRef>()
+        .expect("No state found")
+        .clone();
+
+    let promise = message.get_promise();
+
+    match message.get_event() {
+        v8::PromiseRejectEvent::PromiseRejectWithNoHandler => {
+            let reason = message
+                .get_value()
+                .unwrap_or_else(|| v8::undefined(scope).into());
+
+            let promise = Global::new(scope, promise);
+            let reason = Global::new(scope, reason);
+
+            state.borrow_mut().rejections.reject(promise, reason);
+        }
+        v8::PromiseRejectEvent::PromiseHandlerAddedAfterReject => {
+            state.borrow_mut().rejections.handle(promise);
+        }
+        _ => {}
+    }
+}
+
+/// Exceptions escaping every TryCatch, it is too late to dispatch an event
+pub(crate) extern "C" fn message_callback(message: Local<v8::Message>, value: Local<Value>) {
+    let scope = &mut unsafe { v8::CallbackScope::new(message) };
+    let scope = &mut HandleScope::new(scope);
+
+    let state = scope
+        .get_slot::<JsStateRef>()
+        .expect("No state found")
+        .clone();
+
+    let error = JsError::from_exception(scope, value);
+
+    record(&state, ErrorKind::UncaughtException, error);
+}
+
+fn record(state: &JsStateRef, kind: ErrorKind, error: JsError) {
+    let mut state = state.borrow_mut();
+
+    let request_id = state.request_id.clone();
+
+    state.errors.push(ErrorReport {
+        kind,
+        error,
+        request_id,
+    });
+}
+
+/// Dispatch an event built with the global `class`, returns false when a
+/// listener called `preventDefault`
+fn dispatch_event<'s>(
+    scope: &mut HandleScope<'s>,
+    class: &str,
+    kind: &str,
+    init: Local<'s, v8::Object>,
+) -> bool {
+    let tc = &mut v8::TryCatch::new(scope);
+
+    let global = tc.get_current_context().global(tc);
+
+    let class = v8::String::new(tc, class).unwrap();
+    let dispatch = v8::String::new(tc, "dispatchEvent").unwrap();
+
+    let class = global
+        .get(tc, class.into())
+        .and_then(|class| Local::<v8::Function>::try_from(class).ok());
+    let dispatch = global
+        .get(tc, dispatch.into())
+        .and_then(|dispatch| Local::<v8::Function>::try_from(dispatch).ok());
+
+    let (class, dispatch) = match (class, dispatch) {
+        (Some(class), Some(dispatch)) => (class, dispatch),
+        _ => return true,
+    };
+
+    let cancelable = v8::Boolean::new(tc, true);
+    utils::assign(tc, init, "cancelable", cancelable.into());
+
+    let kind_str = v8::String::new(tc, kind).unwrap();
+
+    let result = class
+        .new_instance(tc, &[kind_str.into(), init.into()])
+        .and_then(|event| dispatch.call(tc, global.into(), &[event.into()]));
+
+    match result {
+        Some(result) => result.boolean_value(tc),
+        None => {
+            if tc.has_caught() {
+                println!(
+                    "Uncaught exception in {} listener: {}",
+                    kind,
+                    JsError::from_try_catch(tc)
+                );
+            }
+
+            true
+        }
+    }
+}
+
+/// Dispatch `error` for an exception thrown by a callback, the error is
+/// recorded for the host unless a listener prevented it
+pub(crate) fn report_exception<'s>(
+    scope: &mut HandleScope<'s>,
+    state: &JsStateRef,
+    exception: Local<'s, Value>,
+    error: JsError,
+) {
+    let init = v8::Object::new(scope);
+
+    utils::assign_string(scope, init, "message", error.message.clone());
+
+    if let Some(script_name) = &error.script_name {
+        utils::assign_string(scope, init, "filename", script_name.clone());
+    }
+
+    if let (Some(line), Some(column)) = (error.line, error.column) {
+        let line = v8::Integer::new(scope, line as i32);
+        let column = v8::Integer::new(scope, column as i32);
+
+        utils::assign(scope, init, "lineno", line.into());
+        utils::assign(scope, init, "colno", column.into());
+    }
+
+    utils::assign(scope, init, "error", exception);
+
+    if dispatch_event(scope, "ErrorEvent", "error", init) {
+        record(state, ErrorKind::UncaughtException, error);
+    }
+}
+
+/// Dispatch `unhandledrejection` for promises still rejected without
+/// handler, those not prevented by a listener are recorded for the host
+pub(crate) fn process_rejections(scope: &mut HandleScope, state: &JsStateRef) {
+    let pending = state.borrow_mut().rejections.take();
+
+    for (promise, reason) in pending {
+        let promise = Local::new(scope, promise);
+        let reason = Local::new(scope, reason);
+
+        let init = v8::Object::new(scope);
+        utils::assign(scope, init, "promise", promise.into());
+        utils::assign(scope, init, "reason", reason);
+
+        if dispatch_event(scope, "PromiseRejectionEvent", "unhandledrejection", init) {
+            let error = JsError::from_exception(scope, reason);
+            record(state, ErrorKind::UnhandledRejection, error);
+        }
+    }
+}
@@ -6,61 +6,43 @@ use v8::Isolate;
 use v8::Local;
 
 use std::cell::RefCell;
-use std::fmt::Write;
+use std::ffi::c_void;
 use std::rc::Rc;
 
+use crate::fetch::HttpClient;
 use crate::utils;
 use crate::utils::init::initialize_v8;
+use crate::utils::init::initialize_v8_with_flags;
+use crate::utils::init::load_script;
 use crate::utils::inspect::inspect_v8_value;
 
+use super::console::ConsoleSink;
+use super::console::LogRecord;
+use super::encoding;
+use super::limits::near_heap_limit_callback;
+use super::limits::HeapState;
+use super::limits::Watchdog;
+use super::module;
+use super::ops;
+use super::options::default_extensions;
+use super::options::Binding;
+use super::options::RuntimeOptions;
+use super::report;
+use super::timers;
+use super::url;
+use super::ErrorReport;
+use super::EvalError;
+use super::HeapLimits;
+use super::JsError;
 use super::JsState;
 use super::JsStateRef;
-use super::RuntimeBasicMessage;
-use super::RuntimeMessage;
-
-#[derive(Debug, PartialEq)]
-pub enum EvalError {
-    CompileError,
-    RuntimeError,
-    ConversionError,
-}
-
-impl std::fmt::Display for EvalError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
-    }
-}
-
-impl std::error::Error for EvalError {}
 
 pub struct JsRuntime {
     pub(crate) isolate: v8::OwnedIsolate,
     pub(crate) context: Global<Context>,
-}
-
-extern "C" fn promise_reject_callback(message: v8::PromiseRejectMessage) {
-    let scope = &mut unsafe { v8::CallbackScope::new(&message) };
-
-    print!("Promise rejected {:?}", message.get_event());
-
-    match message.get_value() {
-        None => print!(" value=None"),
-        Some(value) => print!(" value=Some({})", value.to_rust_string_lossy(scope)),
-    }
-
-    println!(" {:?}", message.get_promise());
-}
-
-extern "C" fn message_callback(message: v8::Local<v8::Message>, value: v8::Local<v8::Value>) {
-    let scope = &mut unsafe { v8::CallbackScope::new(message) };
-    let scope = &mut v8::HandleScope::new(scope);
-    let message_str = message.get(scope);
-
-    println!(
-        "Message callback {:?} {:?}",
-        message_str.to_rust_string_lossy(scope),
-        inspect_v8_value(value, scope)
-    );
+    // Must be dropped after the isolate, its address is given to V8
+    heap: Box<HeapState>,
+    watchdog: Watchdog,
 }
 
 fn message_from_worker(
@@ -81,44 +63,52 @@ fn message_from_worker(
 
     match kind.as_str() {
         "console" => {
-            let mut output = String::new();
-
             let level = utils::get(scope, message, "level").to_rust_string_lossy(scope);
+            let level = match level.parse() {
+                Ok(level) => level,
+                Err(_) => {
+                    println!("Unknown console level: {}", level);
+                    return;
+                }
+            };
 
             let date = utils::get(scope, message, "date")
                 .integer_value(scope)
                 .unwrap_or(0);
-            let date = chrono::NaiveDateTime::from_timestamp_millis(date).unwrap();
+            let timestamp = chrono::NaiveDateTime::from_timestamp_millis(date).unwrap();
 
             let args = utils::get(scope, message, "args");
 
             let args: Local<'_, v8::Array> = args.try_into().unwrap();
 
-            for i in 0..args.length() {
-                let arg = args.get_index(scope, i).unwrap();
-                write!(output, " {}", arg.to_rust_string_lossy(scope)).unwrap();
-            }
-
-            println!("[{:?}] console.{}:{}", date, level, output);
-        }
-        "timer" => {
-            let delay = utils::get(scope, message, "delay");
+            let args = (0..args.length())
+                .map(|i| {
+                    let arg = args.get_index(scope, i).unwrap();
 
-            let time = delay.integer_value(scope).unwrap_or(0);
-
-            let state = scope.get_slot::<JsStateRef>().unwrap();
-            let mut state = state.borrow_mut();
-
-            if delay.is_null_or_undefined() {
-                println!("Clear timer");
-                state.timer = None;
-            } else {
-                println!("Set timer: {:?}", time);
+                    match arg.is_string() {
+                        true => arg.to_rust_string_lossy(scope),
+                        false => inspect_v8_value(arg, scope),
+                    }
+                })
+                .collect();
 
-                let delay = std::time::Duration::from_millis(time as u64);
+            let state = scope.get_slot::<JsStateRef>().expect("No state found");
+            let (sink, worker_id, request_id) = {
+                let state = state.borrow();
+                (
+                    state.console.clone(),
+                    state.worker_id.clone(),
+                    state.request_id.clone(),
+                )
+            };
 
-                state.timer = Some(std::time::Instant::now() + delay);
-            }
+            sink.log(LogRecord {
+                level,
+                timestamp,
+                worker_id,
+                request_id,
+                args,
+            });
         }
         _ => {
             println!("Unknown message kind: {}", kind);
@@ -170,6 +160,34 @@ fn register_message_handler(
     };
 }
 
+fn set_function<'s>(
+    scope: &mut HandleScope<'s>,
+    object: Local<'s, v8::Object>,
+    name: &str,
+    callback: impl v8::MapFnTo<v8::FunctionCallback>,
+) {
+    let function = v8::FunctionTemplate::new(scope, callback);
+    let function = function.get_function(scope).unwrap();
+
+    utils::assign(scope, object, name, function.into());
+}
+
+/// Set the TextEncoder, TextDecoder and base64 functions, wrapped by
+/// encoding.js and base64.js. They are part of snapshots
+fn set_encoding_functions<'s>(scope: &mut HandleScope<'s>, global: Local<'s, v8::Object>) {
+    set_function(scope, global, "__encode", encoding::encode);
+    set_function(scope, global, "__encodeInto", encoding::encode_into);
+    set_function(
+        scope,
+        global,
+        "__encodingForLabel",
+        encoding::encoding_for_label,
+    );
+    set_function(scope, global, "__decode", encoding::decode_callback);
+    set_function(scope, global, "__atob", encoding::atob);
+    set_function(scope, global, "__btoa", encoding::btoa);
+}
+
 fn eval(scope: &mut HandleScope, code: &str) {
     let source = v8::String::new(scope, code).unwrap();
     let script = v8::Script::compile(scope, source, None).unwrap();
@@ -180,7 +198,7 @@ impl JsRuntime {
     pub fn create_snapshot() {
         initialize_v8();
 
-        let mut isolate = Isolate::snapshot_creator(None);
+        let mut isolate = Isolate::snapshot_creator(Some(&**encoding::external_references()));
 
         {
             let scope = &mut HandleScope::new(&mut isolate);
@@ -189,17 +207,12 @@ impl JsRuntime {
 
             let scope = &mut ContextScope::new(scope, context);
 
-            eval(scope, include_str!("../runtime/init.js"));
-            eval(scope, include_str!("../runtime/atob.js"));
-            eval(scope, include_str!("../runtime/btoa.js"));
-            eval(scope, include_str!("../runtime/console.js"));
-            eval(scope, include_str!("../runtime/navigator.js"));
-            eval(scope, include_str!("../runtime/events.js"));
-            eval(scope, include_str!("../runtime/timers.js"));
-            eval(scope, include_str!("../runtime/fetch/headers.js"));
-            eval(scope, include_str!("../runtime/fetch/response.js"));
-            eval(scope, include_str!("../runtime/fetch/request.js"));
-            eval(scope, include_str!("../runtime/fetch/fetch-event.js"));
+            let global = context.global(scope);
+            set_encoding_functions(scope, global);
+
+            for extension in default_extensions() {
+                eval(scope, &extension.source);
+            }
 
             scope.set_default_context(context);
         }
@@ -210,26 +223,86 @@ impl JsRuntime {
         std::fs::write("snapshot.bin", snapshot).unwrap();
     }
 
-    /// Create a new context with default extensions
+    /// Create a new context with default extensions, panics if they fail
     pub fn create_init(snapshot: Option<Vec<u8>>) -> Self {
-        initialize_v8();
+        Self::create_init_with_limits(snapshot, None)
+    }
+
+    /// Create a new context with default extensions and bounded heap size
+    pub fn create_init_with_limits(
+        snapshot: Option<Vec<u8>>,
+        heap_limits: Option<HeapLimits>,
+    ) -> Self {
+        Self::new(RuntimeOptions {
+            snapshot,
+            heap_limits,
+            ..Default::default()
+        })
+        .expect("Default extensions failed")
+    }
+
+    /// Create a new context configured by `options`, see `JsRuntimeBuilder`.
+    /// Fails if an extension throws, or is not a default one while restoring
+    /// a snapshot
+    pub fn new(options: RuntimeOptions) -> Result<Self, EvalError> {
+        let RuntimeOptions {
+            snapshot,
+            extensions,
+            heap_limits,
+            v8_flags,
+            console,
+            time_limit,
+            http_client,
+            bindings,
+        } = options;
+
+        initialize_v8_with_flags(&v8_flags);
 
         let time = std::time::Instant::now();
 
         let from_snapshot = snapshot.is_some();
 
+        // Snapshots only hold the default extensions
+        if from_snapshot {
+            let defaults = default_extensions();
+
+            let added = extensions.iter().find(|extension| {
+                !defaults.iter().any(|default| {
+                    default.name == extension.name && default.source == extension.source
+                })
+            });
+
+            if let Some(extension) = added {
+                println!("Cannot add extension {} to a snapshot", extension.name);
+                return Err(EvalError::ExtensionsWithSnapshot);
+            }
+        }
+
         let mut rt = {
-            let mut isolate = match snapshot {
-                Some(snapshot) => {
-                    let params = v8::Isolate::create_params().snapshot_blob(snapshot);
-                    Isolate::new(params)
-                }
-                None => Isolate::new(Default::default()),
+            let params = v8::Isolate::create_params()
+                .external_references(&**encoding::external_references());
+
+            let params = match snapshot {
+                Some(snapshot) => params.snapshot_blob(snapshot),
+                None => params,
+            };
+
+            let params = match heap_limits {
+                Some(limits) => params.heap_limits(limits.initial, limits.max),
+                None => params,
             };
 
+            let mut isolate = Isolate::new(params);
+
+            // Terminate execution instead of aborting the process on OOM
+            let heap = Box::new(HeapState::new(isolate.thread_safe_handle()));
+            let data = &*heap as *const HeapState as *mut c_void;
+            isolate.add_near_heap_limit_callback(near_heap_limit_callback, data);
+
             isolate.set_capture_stack_trace_for_uncaught_exceptions(false, 0);
-            isolate.set_promise_reject_callback(promise_reject_callback);
-            isolate.add_message_listener(message_callback);
+            isolate.set_promise_reject_callback(report::promise_reject_callback);
+            isolate.add_message_listener(report::message_callback);
+            isolate.set_host_import_module_dynamically_callback(module::dynamic_import_callback);
 
             let context = {
                 let scope = &mut HandleScope::new(&mut isolate);
@@ -245,25 +318,29 @@ impl JsRuntime {
                 context
             };
 
-            JsRuntime { isolate, context }
+            JsRuntime {
+                isolate,
+                context,
+                heap,
+                watchdog: Watchdog::default(),
+            }
         };
 
         if !from_snapshot {
-            rt.eval(include_str!("../runtime/init.js")).unwrap();
-            rt.eval(include_str!("../runtime/atob.js")).unwrap();
-            rt.eval(include_str!("../runtime/btoa.js")).unwrap();
-            rt.eval(include_str!("../runtime/console.js")).unwrap();
-            rt.eval(include_str!("../runtime/navigator.js")).unwrap();
-            rt.eval(include_str!("../runtime/events.js")).unwrap();
-            rt.eval(include_str!("../runtime/timers.js")).unwrap();
-            rt.eval(include_str!("../runtime/fetch/headers.js"))
-                .unwrap();
-            rt.eval(include_str!("../runtime/fetch/response.js"))
-                .unwrap();
-            rt.eval(include_str!("../runtime/fetch/request.js"))
-                .unwrap();
-            rt.eval(include_str!("../runtime/fetch/fetch-event.js"))
-                .unwrap();
+            // Set encoding functions before extensions can use them, they are
+            // restored from the snapshot otherwise
+            {
+                let scope = &mut HandleScope::new(&mut rt.isolate);
+                let context = Local::new(scope, &rt.context);
+                let global = context.global(scope);
+                let scope = &mut ContextScope::new(scope, context);
+
+                set_encoding_functions(scope, global);
+            }
+
+            for extension in extensions {
+                rt.execute_script(&extension.name, &extension.source)?;
+            }
         }
 
         let time = time.elapsed().as_micros();
@@ -297,34 +374,321 @@ impl JsRuntime {
             global.set(scope, name.into(), on_message.into());
         }
 
+        // Set timers and async ops functions
+        {
+            let scope = &mut HandleScope::new(&mut rt.isolate);
+            let context = Local::new(scope, &rt.context);
+            let global = context.global(scope);
+            let scope = &mut ContextScope::new(scope, context);
+
+            set_function(scope, global, "setTimeout", timers::set_timeout);
+            set_function(scope, global, "setInterval", timers::set_interval);
+            set_function(scope, global, "clearTimeout", timers::clear_timer);
+            set_function(scope, global, "clearInterval", timers::clear_timer);
+            set_function(
+                scope,
+                global,
+                "__setUnrefTimeout",
+                timers::set_unref_timeout,
+            );
+            set_function(scope, global, "__cancelOp", ops::cancel_op);
+        }
+
+        // Set URL functions, wrapped by url.js
+        {
+            let scope = &mut HandleScope::new(&mut rt.isolate);
+            let context = Local::new(scope, &rt.context);
+            let global = context.global(scope);
+            let scope = &mut ContextScope::new(scope, context);
+
+            set_function(scope, global, "__urlParse", url::parse);
+            set_function(scope, global, "__urlSet", url::set);
+            set_function(
+                scope,
+                global,
+                "__urlSearchParamsParse",
+                url::search_params_parse,
+            );
+            set_function(
+                scope,
+                global,
+                "__urlSearchParamsStringify",
+                url::search_params_stringify,
+            );
+        }
+
+        // Set fetch functions, wrapped by fetch.js and form-data.js
+        {
+            let scope = &mut HandleScope::new(&mut rt.isolate);
+            let context = Local::new(scope, &rt.context);
+            let global = context.global(scope);
+            let scope = &mut ContextScope::new(scope, context);
+
+            set_function(
+                scope,
+                global,
+                "__fetch",
+                crate::fetch::client::fetch_callback,
+            );
+            set_function(
+                scope,
+                global,
+                "__parseMultipart",
+                crate::fetch::multipart::parse_multipart_callback,
+            );
+        }
+
+        // Set embedder bindings
+        {
+            let scope = &mut HandleScope::new(&mut rt.isolate);
+            let context = Local::new(scope, &rt.context);
+            let global = context.global(scope);
+            let scope = &mut ContextScope::new(scope, context);
+
+            for (name, binding) in bindings {
+                let value: Local<v8::Value> = match binding {
+                    Binding::Function(callback) => v8::Function::builder_raw(callback)
+                        .build(scope)
+                        .unwrap()
+                        .into(),
+                    Binding::String(value) => v8::String::new(scope, &value).unwrap().into(),
+                    Binding::Number(value) => v8::Number::new(scope, value).into(),
+                    Binding::Boolean(value) => v8::Boolean::new(scope, value).into(),
+                };
+
+                utils::assign(scope, global, &name, value);
+            }
+        }
+
+        if let Some(console) = console {
+            let state = rt.isolate.get_slot::<JsStateRef>().expect("No state found");
+            state.borrow_mut().console = console;
+        }
+
+        if let Some(client) = http_client {
+            let state = rt.isolate.get_slot::<JsStateRef>().expect("No state found");
+            state.borrow_mut().http_client = Some(client);
+        }
+
+        rt.set_time_limit(time_limit);
+
         // Runtime message handler
-        rt.eval(include_str!("../runtime/message.js")).unwrap();
+        rt.eval(include_str!("../runtime/message.js"))?;
 
-        rt
+        Ok(rt)
     }
 
     /// Evaluate a script
     pub fn eval(&mut self, script: &str) -> Result<String, EvalError> {
+        self.execute_script("<eval>", script)
+    }
+
+    /// Evaluate a script, `name` is used as script name in error locations
+    pub fn execute_script(&mut self, name: &str, script: &str) -> Result<String, EvalError> {
+        let heap = &self.heap;
+        let watchdog = &self.watchdog;
         let scope = &mut HandleScope::new(&mut self.isolate);
 
         let context = Local::new(scope, &self.context);
         let scope = &mut ContextScope::new(scope, context);
+        let tc = &mut v8::TryCatch::new(scope);
+
+        let script = match load_script(tc, name, script) {
+            Some(script) => script,
+            None => return Err(EvalError::CompileError(JsError::from_try_catch(tc))),
+        };
 
-        let code = v8::String::new(scope, script).ok_or(EvalError::CompileError)?;
-        let script = v8::Script::compile(scope, code, None).ok_or(EvalError::CompileError)?;
+        let state = tc.get_slot::<JsStateRef>().expect("No state found").clone();
 
         // Run script
-        let result = script.run(scope).ok_or(EvalError::RuntimeError)?;
+        watchdog.start();
+        let result = script.run(tc);
+        if result.is_some() {
+            report::process_rejections(tc, &state);
+        }
+        let timed_out = watchdog.stop();
+
+        let result = match result {
+            None if heap.is_exhausted() => return Err(EvalError::HeapLimitExceeded),
+            _ if timed_out => {
+                tc.cancel_terminate_execution();
+                return Err(EvalError::TimeLimitExceeded);
+            }
+            Some(result) => result,
+            None => return Err(EvalError::RuntimeError(JsError::from_try_catch(tc))),
+        };
+
+        let result = result.to_string(tc).ok_or(EvalError::ConversionError)?;
+
+        Ok(result.to_rust_string_lossy(tc))
+    }
+
+    /// Replace the loader used to resolve and fetch ES modules
+    pub fn set_module_loader<L: module::ModuleLoader + 'static>(&mut self, loader: L) {
+        let state = self
+            .isolate
+            .get_slot::<JsStateRef>()
+            .expect("No state found");
+
+        state.borrow_mut().loader = Rc::new(loader);
+    }
+
+    /// Load an ES module and its dependencies, then evaluate the graph
+    /// (including top-level await) by driving the event loop
+    pub async fn load_main_module(&mut self, specifier: &str) -> Result<(), EvalError> {
+        let watchdog = &self.watchdog;
+
+        let promise = {
+            let scope = &mut HandleScope::new(&mut self.isolate);
+            let context = Local::new(scope, &self.context);
+            let scope = &mut ContextScope::new(scope, context);
+            let tc = &mut v8::TryCatch::new(scope);
+
+            let state = tc.get_slot::<JsStateRef>().expect("No state found");
+            let loader = state.borrow().loader.clone();
+            let name = loader.resolve(specifier, None)?;
+
+            watchdog.start();
+            let evaluated = module::evaluate_module(tc, &name);
+
+            if watchdog.stop() {
+                tc.cancel_terminate_execution();
+                return Err(EvalError::TimeLimitExceeded);
+            }
+
+            let (_, result) = evaluated?;
+
+            // With top-level await support, evaluation always returns a promise
+            let promise: Local<v8::Promise> =
+                result.try_into().map_err(|_| EvalError::ConversionError)?;
+
+            // A rejection is returned as an error, not reported as unhandled
+            promise.mark_as_handled();
+
+            Global::new(tc, promise)
+        };
+
+        self.run_event_loop().await?;
+
+        let scope = &mut HandleScope::new(&mut self.isolate);
+        let promise = Local::new(scope, promise);
+
+        match promise.state() {
+            v8::PromiseState::Fulfilled => Ok(()),
+            v8::PromiseState::Rejected => {
+                let exception = promise.result(scope);
+                Err(EvalError::RuntimeError(JsError::from_exception(
+                    scope, exception,
+                )))
+            }
+            v8::PromiseState::Pending => {
+                println!("Module evaluation never settled: {}", specifier);
+                Err(EvalError::RuntimeError(JsError {
+                    message: String::from("Top-level await never settled"),
+                    script_name: Some(specifier.to_string()),
+                    ..Default::default()
+                }))
+            }
+        }
+    }
+
+    /// Whether execution was terminated because the heap limit was reached,
+    /// the runtime should be dropped and recreated
+    pub fn is_heap_exhausted(&self) -> bool {
+        self.heap.is_exhausted()
+    }
+
+    /// Terminate evals, handler calls and microtask checkpoints running for
+    /// longer than `limit`, the runtime remains usable afterwards
+    pub fn set_time_limit(&mut self, limit: Option<std::time::Duration>) {
+        let handle = self.isolate.thread_safe_handle();
+
+        self.watchdog.set_limit(handle, limit);
+    }
+
+    /// Send the console output of the worker to `sink`
+    pub fn set_console_sink<S: ConsoleSink + 'static>(&mut self, sink: S) {
+        let state = self
+            .isolate
+            .get_slot::<JsStateRef>()
+            .expect("No state found");
+
+        state.borrow_mut().console = Rc::new(sink);
+    }
+
+    /// Replace the client used by `fetch()`
+    pub fn set_http_client<C: HttpClient + 'static>(&mut self, client: C) {
+        let state = self
+            .isolate
+            .get_slot::<JsStateRef>()
+            .expect("No state found");
+
+        state.borrow_mut().http_client = Some(Rc::new(client));
+    }
 
-        let result = result.to_string(scope).ok_or(EvalError::ConversionError)?;
+    /// Attach `worker_id` to the console records of this runtime
+    pub fn set_worker_id(&mut self, worker_id: Option<String>) {
+        let state = self
+            .isolate
+            .get_slot::<JsStateRef>()
+            .expect("No state found");
 
-        Ok(result.to_rust_string_lossy(scope))
+        state.borrow_mut().worker_id = worker_id;
+    }
+
+    /// Attach `request_id` to the errors and console records reported until
+    /// it is changed
+    pub fn set_request_id(&mut self, request_id: Option<String>) {
+        let state = self
+            .isolate
+            .get_slot::<JsStateRef>()
+            .expect("No state found");
+
+        state.borrow_mut().request_id = request_id;
+    }
+
+    /// Uncaught exceptions and unhandled rejections reported since the last
+    /// call, errors prevented by an `error` or `unhandledrejection`
+    /// listener are not reported
+    pub fn take_error_reports(&mut self) -> Vec<ErrorReport> {
+        let state = self
+            .isolate
+            .get_slot::<JsStateRef>()
+            .expect("No state found");
+
+        std::mem::take(&mut state.borrow_mut().errors)
+    }
+
+    /// Drop the pending timers and ops started while the current request
+    /// id was set (see `set_request_id`), e.g. work left once the request is
+    /// done. Their callbacks never run and their promises never settle,
+    /// work started by the script itself is kept
+    pub fn cancel_pending(&mut self) {
+        let state = self
+            .isolate
+            .get_slot::<JsStateRef>()
+            .expect("No state found");
+
+        let mut state = state.borrow_mut();
+
+        if let Some(request_id) = state.request_id.clone() {
+            state.timers.clear_request(&request_id);
+            state.ops.cancel_request(&request_id);
+        }
+    }
+
+    /// Abort the response bodies still being streamed, their receivers get
+    /// `error` instead of the end of the body
+    pub fn abort_response_streams(&mut self, error: &str) {
+        crate::fetch::stream::abort_streams(&mut self.isolate, error);
     }
 
     pub fn send_message<E: super::message::RuntimeMessage>(
         &mut self,
         event: &mut E,
-    ) -> Option<Local<v8::Value>> {
+    ) -> Result<Local<v8::Value>, EvalError> {
+        let heap = &self.heap;
+        let watchdog = &self.watchdog;
         let scope = &mut HandleScope::new(&mut self.isolate);
         let context = Local::new(scope, &self.context);
 
@@ -332,19 +696,35 @@ impl JsRuntime {
 
         let result = {
             let scope = &mut ContextScope::new(scope, context);
+            let tc = &mut v8::TryCatch::new(scope);
 
             // Prepare handler call
-            let handler = Self::get_handler(scope).unwrap();
-            let undefined = v8::undefined(scope).into();
+            let handler = Self::get_handler(tc).unwrap();
+            let undefined = v8::undefined(tc).into();
+
+            let event = event.to_value(tc);
 
-            let event = event.to_value(scope);
+            let state = tc.get_slot::<JsStateRef>().expect("No state found").clone();
 
             // Call handler
-            let result = handler.call(scope, undefined, &[event]);
+            watchdog.start();
+            let result = handler.call(tc, undefined, &[event]);
+            if result.is_some() {
+                report::process_rejections(tc, &state);
+            }
+            let timed_out = watchdog.stop();
 
             println!("Event result: {:?}", result);
 
-            result
+            match result {
+                None if heap.is_exhausted() => Err(EvalError::HeapLimitExceeded),
+                _ if timed_out => {
+                    tc.cancel_terminate_execution();
+                    Err(EvalError::TimeLimitExceeded)
+                }
+                Some(result) => Ok(result),
+                None => Err(EvalError::RuntimeError(JsError::from_try_catch(tc))),
+            }
         };
 
         result
@@ -370,83 +750,126 @@ impl JsRuntime {
         Some(handler)
     }
 
-    fn poll_timer(
-        cx: &mut std::task::Context,
+    /// Run the callbacks of timers due now, with a microtask checkpoint
+    /// after each of them
+    fn run_timers(
         scope: &mut v8::ContextScope<v8::HandleScope>,
-    ) -> std::task::Poll<bool> {
-        let state = scope.get_slot::<JsStateRef>().expect("No state found");
+        state: &JsStateRef,
+        heap: &HeapState,
+        watchdog: &Watchdog,
+    ) -> Result<(), EvalError> {
+        let now = std::time::Instant::now();
 
-        let timer = state.borrow_mut().timer;
+        loop {
+            let timer = state.borrow_mut().timers.pop_due(now);
 
-        let now = std::time::Instant::now();
+            let timer = match timer {
+                Some(timer) => timer,
+                None => return Ok(()),
+            };
 
-        match timer {
-            None => {
-                println!("Timer [void]");
-                std::task::Poll::Ready(true)
-            }
-            Some(time) => {
-                if time > now {
-                    let waker = cx.waker().clone();
-
-                    tokio::task::spawn(async move {
-                        tokio::time::sleep_until(time.into()).await;
-                        println!("Timer waked");
-                        waker.wake();
-                    });
-
-                    println!("Timer [pending]");
-
-                    std::task::Poll::Pending
-                } else {
-                    state.borrow_mut().timer = None;
-                    println!("Timer fired");
-
-                    // Wait for timer
-                    {
-                        let cb = v8::Function::new(
-                            scope,
-                            |scope: &mut v8::HandleScope,
-                             _args: v8::FunctionCallbackArguments,
-                             _rv: v8::ReturnValue| {
-                                // Prepare handler call
-                                let handler = Self::get_handler(scope).unwrap();
-                                let undefined = v8::undefined(scope).into();
-
-                                let message = RuntimeBasicMessage::new(String::from("timer"));
-                                let message = message.to_value(scope);
-
-                                // Call handler
-                                handler.call(scope, undefined, &[message]);
-                            },
-                        )
-                        .unwrap();
-
-                        scope.enqueue_microtask(cb)
-                    }
+            let tc = &mut v8::TryCatch::new(scope);
+
+            let callback = Local::new(tc, &timer.callback);
+            let args: Vec<Local<v8::Value>> =
+                timer.args.iter().map(|arg| Local::new(tc, arg)).collect();
+            let global = tc.get_current_context().global(tc).into();
+
+            watchdog.start();
+            let result = callback.call(tc, global, &args);
 
-                    std::task::Poll::Ready(false)
+            if result.is_none() && tc.can_continue() {
+                if let Some(exception) = tc.exception() {
+                    let error = JsError::from_try_catch(tc);
+                    tc.reset();
+                    report::report_exception(tc, state, exception, error);
                 }
             }
+
+            tc.perform_microtask_checkpoint();
+            report::process_rejections(tc, state);
+            let timed_out = watchdog.stop();
+
+            state.borrow_mut().timers.leave();
+
+            if heap.is_exhausted() {
+                return Err(EvalError::HeapLimitExceeded);
+            }
+
+            if timed_out {
+                tc.cancel_terminate_execution();
+                return Err(EvalError::TimeLimitExceeded);
+            }
         }
     }
 
-    pub async fn run_event_loop<'a>(&mut self) {
+    pub async fn run_event_loop<'a>(&mut self) -> Result<(), EvalError> {
+        let heap = &self.heap;
+        let watchdog = &self.watchdog;
         let scope = &mut HandleScope::new(&mut self.isolate);
         let context = Local::new(scope, &self.context);
         let scope = &mut ContextScope::new(scope, context);
 
+        let state = scope
+            .get_slot::<JsStateRef>()
+            .expect("No state found")
+            .clone();
+
         loop {
+            watchdog.start();
             scope.perform_microtask_checkpoint();
+            report::process_rejections(scope, &state);
+            let timed_out = watchdog.stop();
+
+            if heap.is_exhausted() {
+                return Err(EvalError::HeapLimitExceeded);
+            }
 
-            let timers_task = tokio::macros::support::poll_fn(|cx| Self::poll_timer(cx, scope));
+            if timed_out {
+                scope.cancel_terminate_execution();
+                return Err(EvalError::TimeLimitExceeded);
+            }
 
-            let empty = timers_task.await;
+            let deadline = state.borrow_mut().timers.next_deadline();
+            let has_timers = state.borrow().timers.has_refed();
+            let has_ops = !state.borrow().ops.is_empty();
 
-            // Check if we are done
-            if empty {
+            // Check if we are done, unref'd timers are left for later runs
+            if !has_timers && !has_ops {
                 break;
             }
+
+            let timer = async {
+                match deadline {
+                    Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+                    None => std::future::pending().await,
+                }
+            };
+
+            let op = std::future::poll_fn(|cx| state.borrow_mut().ops.poll_next(cx));
+
+            tokio::select! {
+                _ = timer => Self::run_timers(scope, &state, heap, watchdog)?,
+                (resolver, result) = op => {
+                    let scope = &mut HandleScope::new(scope);
+
+                    // Settling may run code, e.g. modules evaluated by import()
+                    watchdog.start();
+                    ops::resolve_op(scope, resolver, result);
+                    let timed_out = watchdog.stop();
+
+                    if heap.is_exhausted() {
+                        return Err(EvalError::HeapLimitExceeded);
+                    }
+
+                    if timed_out {
+                        scope.cancel_terminate_execution();
+                        return Err(EvalError::TimeLimitExceeded);
+                    }
+                }
+            }
         }
+
+        Ok(())
     }
 }
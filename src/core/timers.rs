@@ -0,0 +1,252 @@
+use v8::Global;
+use v8::HandleScope;
+use v8::Local;
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::collections::HashMap;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::utils;
+
+use super::JsStateRef;
+
+/// Minimum delay of deeply nested timers, per the HTML spec
+const NESTED_MIN_DELAY: Duration = Duration::from_millis(4);
+const NESTED_MAX_LEVEL: u32 = 5;
+
+struct Timer {
+    callback: Global<v8::Function>,
+    args: Vec<Global<v8::Value>>,
+    interval: Option<Duration>,
+    nesting: u32,
+    /// Unref'd timers do not keep the event loop running
+    refed: bool,
+    /// Request being handled when the timer was scheduled
+    request_id: Option<String>,
+}
+
+/// Callback and arguments of a timer that is due
+pub(crate) struct DueTimer {
+    pub callback: Global<v8::Function>,
+    pub args: Vec<Global<v8::Value>>,
+}
+
+/// Pending timers ordered by deadline, then by scheduling order
+#[derive(Default)]
+pub struct TimerQueue {
+    next_id: u32,
+    sequence: u64,
+    timers: HashMap<u32, Timer>,
+    queue: BinaryHeap<Reverse<(Instant, u64, u32)>>,
+    // Nesting level of the timer being run, 0 outside of timer callbacks
+    nesting: u32,
+}
+
+impl TimerQueue {
+    fn schedule(
+        &mut self,
+        callback: Global<v8::Function>,
+        args: Vec<Global<v8::Value>>,
+        delay: Duration,
+        repeat: bool,
+        refed: bool,
+        request_id: Option<String>,
+    ) -> u32 {
+        self.next_id += 1;
+        let id = self.next_id;
+
+        let (clamped, nesting) = self.clamp(delay);
+
+        let timer = Timer {
+            callback,
+            args,
+            interval: if repeat { Some(delay) } else { None },
+            nesting,
+            refed,
+            request_id,
+        };
+
+        self.timers.insert(id, timer);
+        self.push(id, clamped);
+
+        id
+    }
+
+    fn clear(&mut self, id: u32) {
+        // Entries left in the queue are skipped once the timer is gone
+        self.timers.remove(&id);
+    }
+
+    /// Apply the HTML spec clamping, returns the delay and the nesting
+    /// level of the new task
+    fn clamp(&self, delay: Duration) -> (Duration, u32) {
+        let delay = match self.nesting > NESTED_MAX_LEVEL && delay < NESTED_MIN_DELAY {
+            true => NESTED_MIN_DELAY,
+            false => delay,
+        };
+
+        (delay, self.nesting + 1)
+    }
+
+    fn push(&mut self, id: u32, delay: Duration) {
+        self.sequence += 1;
+        self.queue
+            .push(Reverse((Instant::now() + delay, self.sequence, id)));
+    }
+
+    /// Drop the timers scheduled while handling `request_id`
+    pub(crate) fn clear_request(&mut self, request_id: &str) {
+        // Entries left in the queue are skipped once the timer is gone
+        self.timers
+            .retain(|_, timer| timer.request_id.as_deref() != Some(request_id));
+    }
+
+    /// Whether a timer keeps the event loop running
+    pub fn has_refed(&self) -> bool {
+        self.timers.values().any(|timer| timer.refed)
+    }
+
+    /// Deadline of the next timer to fire, if any
+    pub fn next_deadline(&mut self) -> Option<Instant> {
+        while let Some(Reverse((deadline, _, id))) = self.queue.peek().copied() {
+            if self.timers.contains_key(&id) {
+                return Some(deadline);
+            }
+
+            self.queue.pop();
+        }
+
+        None
+    }
+
+    /// Take the next timer due at `now`, intervals are rescheduled before
+    /// their callback runs so that they can clear themselves
+    pub(crate) fn pop_due(&mut self, now: Instant) -> Option<DueTimer> {
+        loop {
+            let Reverse((deadline, _, id)) = self.queue.peek().copied()?;
+
+            if deadline > now {
+                return None;
+            }
+
+            self.queue.pop();
+
+            let timer = match self.timers.get(&id) {
+                Some(timer) => timer,
+                None => continue,
+            };
+
+            let due = DueTimer {
+                callback: timer.callback.clone(),
+                args: timer.args.clone(),
+            };
+
+            let interval = timer.interval;
+            self.nesting = timer.nesting;
+
+            match interval {
+                Some(interval) => {
+                    let (interval, nesting) = self.clamp(interval);
+
+                    if let Some(timer) = self.timers.get_mut(&id) {
+                        timer.nesting = nesting;
+                    }
+
+                    self.push(id, interval);
+                }
+                None => {
+                    self.timers.remove(&id);
+                }
+            }
+
+            return Some(due);
+        }
+    }
+
+    /// Mark the end of a timer callback
+    pub(crate) fn leave(&mut self) {
+        self.nesting = 0;
+    }
+}
+
+fn schedule(
+    scope: &mut HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+    repeat: bool,
+    refed: bool,
+) {
+    let callback: Local<v8::Function> = match args.get(0).try_into() {
+        Ok(callback) => callback,
+        Err(_) => {
+            utils::throw_type_error(scope, "Callback must be a function");
+            return;
+        }
+    };
+
+    // Negative, NaN or missing delays are treated as 0
+    let delay = args.get(1).number_value(scope).unwrap_or(0.0);
+    let delay = match delay.is_finite() && delay > 0.0 {
+        true => Duration::from_millis(delay as u64),
+        false => Duration::ZERO,
+    };
+
+    let callback = Global::new(scope, callback);
+    let timer_args = (2..args.length())
+        .map(|i| Global::new(scope, args.get(i)))
+        .collect();
+
+    let state = scope.get_slot::<JsStateRef>().expect("No state found");
+    let id = {
+        let mut state = state.borrow_mut();
+        let request_id = state.request_id.clone();
+
+        state
+            .timers
+            .schedule(callback, timer_args, delay, repeat, refed, request_id)
+    };
+
+    rv.set(v8::Integer::new_from_unsigned(scope, id).into());
+}
+
+pub(crate) fn set_timeout(
+    scope: &mut HandleScope,
+    args: v8::FunctionCallbackArguments,
+    rv: v8::ReturnValue,
+) {
+    schedule(scope, args, rv, false, true);
+}
+
+pub(crate) fn set_interval(
+    scope: &mut HandleScope,
+    args: v8::FunctionCallbackArguments,
+    rv: v8::ReturnValue,
+) {
+    schedule(scope, args, rv, true, true);
+}
+
+/// Timeout that does not keep the event loop running, e.g. for
+/// `AbortSignal.timeout`
+pub(crate) fn set_unref_timeout(
+    scope: &mut HandleScope,
+    args: v8::FunctionCallbackArguments,
+    rv: v8::ReturnValue,
+) {
+    schedule(scope, args, rv, false, false);
+}
+
+pub(crate) fn clear_timer(
+    scope: &mut HandleScope,
+    args: v8::FunctionCallbackArguments,
+    _rv: v8::ReturnValue,
+) {
+    let id = match args.get(0).uint32_value(scope) {
+        Some(id) => id,
+        None => return,
+    };
+
+    let state = scope.get_slot::<JsStateRef>().expect("No state found");
+    state.borrow_mut().timers.clear(id);
+}
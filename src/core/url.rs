@@ -0,0 +1,157 @@
+use v8::HandleScope;
+use v8::Local;
+
+use url::form_urlencoded;
+use url::quirks;
+use url::Url;
+
+use crate::utils;
+
+/// URL components as exposed by the URL class
+fn components<'s>(scope: &mut HandleScope<'s>, url: &Url) -> Local<'s, v8::Object> {
+    let object = v8::Object::new(scope);
+
+    utils::assign_string(scope, object, "href", quirks::href(url).to_string());
+    utils::assign_string(scope, object, "origin", quirks::origin(url));
+    utils::assign_string(scope, object, "protocol", quirks::protocol(url).to_string());
+    utils::assign_string(scope, object, "username", quirks::username(url).to_string());
+    utils::assign_string(scope, object, "password", quirks::password(url).to_string());
+    utils::assign_string(scope, object, "host", quirks::host(url).to_string());
+    utils::assign_string(scope, object, "hostname", quirks::hostname(url).to_string());
+    utils::assign_string(scope, object, "port", quirks::port(url).to_string());
+    utils::assign_string(scope, object, "pathname", quirks::pathname(url).to_string());
+    utils::assign_string(scope, object, "search", quirks::search(url).to_string());
+    utils::assign_string(scope, object, "hash", quirks::hash(url).to_string());
+
+    object
+}
+
+/// __urlParse(input, base?): components of the parsed URL, or null if the
+/// input is not a valid URL
+pub(crate) fn parse(
+    scope: &mut HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut ret: v8::ReturnValue,
+) {
+    let input = args.get(0).to_rust_string_lossy(scope);
+    let base = args.get(1);
+
+    let url = match base.is_undefined() {
+        true => Url::parse(&input),
+        false => {
+            let base = base.to_rust_string_lossy(scope);
+            Url::parse(&base).and_then(|base| Url::options().base_url(Some(&base)).parse(&input))
+        }
+    };
+
+    match url {
+        Ok(url) => ret.set(components(scope, &url).into()),
+        Err(_) => ret.set_null(),
+    }
+}
+
+/// __urlSet(href, name, value): components after setting one of them, values
+/// the setter rejects are ignored like in browsers
+pub(crate) fn set(
+    scope: &mut HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut ret: v8::ReturnValue,
+) {
+    let href = args.get(0).to_rust_string_lossy(scope);
+    let name = args.get(1).to_rust_string_lossy(scope);
+    let value = args.get(2).to_rust_string_lossy(scope);
+
+    let mut url = match Url::parse(&href) {
+        Ok(url) => url,
+        Err(_) => {
+            utils::throw_type_error(scope, &format!("Invalid URL: {}", href));
+            return;
+        }
+    };
+
+    match name.as_str() {
+        "href" => {
+            if quirks::set_href(&mut url, &value).is_err() {
+                utils::throw_type_error(scope, &format!("Invalid URL: {}", value));
+                return;
+            }
+        }
+        "protocol" => quirks::set_protocol(&mut url, &value).unwrap_or(()),
+        "username" => quirks::set_username(&mut url, &value).unwrap_or(()),
+        "password" => quirks::set_password(&mut url, &value).unwrap_or(()),
+        "host" => quirks::set_host(&mut url, &value).unwrap_or(()),
+        "hostname" => quirks::set_hostname(&mut url, &value).unwrap_or(()),
+        "port" => quirks::set_port(&mut url, &value).unwrap_or(()),
+        "pathname" => quirks::set_pathname(&mut url, &value),
+        "search" => quirks::set_search(&mut url, &value),
+        "hash" => quirks::set_hash(&mut url, &value),
+        _ => {
+            utils::throw_type_error(scope, &format!("Unknown URL component: {}", name));
+            return;
+        }
+    }
+
+    ret.set(components(scope, &url).into());
+}
+
+/// __urlSearchParamsParse(query): [name, value] pairs of an
+/// application/x-www-form-urlencoded string
+pub(crate) fn search_params_parse(
+    scope: &mut HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut ret: v8::ReturnValue,
+) {
+    let query = args.get(0).to_rust_string_lossy(scope);
+
+    let pairs: Vec<Local<v8::Value>> = form_urlencoded::parse(query.as_bytes())
+        .map(|(name, value)| {
+            let name = v8::String::new(scope, &name).unwrap().into();
+            let value = v8::String::new(scope, &value).unwrap().into();
+
+            v8::Array::new_with_elements(scope, &[name, value]).into()
+        })
+        .collect();
+
+    ret.set(v8::Array::new_with_elements(scope, &pairs).into());
+}
+
+/// __urlSearchParamsStringify(pairs): application/x-www-form-urlencoded
+/// serialization of [name, value] pairs
+pub(crate) fn search_params_stringify(
+    scope: &mut HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut ret: v8::ReturnValue,
+) {
+    let pairs: Local<v8::Array> = match args.get(0).try_into() {
+        Ok(pairs) => pairs,
+        Err(_) => {
+            utils::throw_type_error(scope, "Search params must be an array");
+            return;
+        }
+    };
+
+    let mut serializer = form_urlencoded::Serializer::new(String::new());
+
+    for index in 0..pairs.length() {
+        let pair = pairs
+            .get_index(scope, index)
+            .and_then(|pair| Local::<v8::Array>::try_from(pair).ok());
+
+        if let Some(pair) = pair {
+            let name = pair
+                .get_index(scope, 0)
+                .unwrap()
+                .to_rust_string_lossy(scope);
+            let value = pair
+                .get_index(scope, 1)
+                .unwrap()
+                .to_rust_string_lossy(scope);
+
+            serializer.append_pair(&name, &value);
+        }
+    }
+
+    let query = v8::String::new(scope, &serializer.finish()).unwrap();
+
+    ret.set(query.into());
+}
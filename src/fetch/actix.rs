@@ -1,40 +1,441 @@
+use actix_web::http::header::HeaderName;
+use actix_web::http::header::HeaderValue;
+use actix_web::http::header::CONTENT_LENGTH;
+use actix_web::http::header::CONTENT_TYPE;
+use actix_web::http::StatusCode;
+use actix_web::web::Bytes;
 use actix_web::HttpRequest;
 use actix_web::HttpResponse;
-use actix_web::http::StatusCode;
+use actix_web::HttpResponseBuilder;
 
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use super::FetchRequest;
+use super::FetchResponse;
 use super::JsRequest;
 use super::JsResponse;
+use super::RedirectMode;
 
 const DEFAULT_CONTENT_TYPE: &str = "text/plain; charset=utf-8";
 
 /// Convert an Actix request into a JS request
 impl Into<JsRequest> for HttpRequest {
     fn into(self) -> JsRequest {
+        let headers = self
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                let value = String::from_utf8_lossy(value.as_bytes()).to_string();
+                (name.to_string(), value)
+            })
+            .collect();
+
+        // Absolute, so that workers can parse it with `new URL(request.url)`
+        let url = {
+            let info = self.connection_info();
+            format!("{}://{}{}", info.scheme(), info.host(), self.uri())
+        };
+
         JsRequest {
-            url: self.uri().to_string(),
+            url,
             method: self.method().to_string(),
-            // headers: vec![],
-            // body: None,
+            headers,
+            body: None,
+        }
+    }
+}
+
+/// Convert an Actix request and its payload into a JS request
+impl Into<JsRequest> for (HttpRequest, Bytes) {
+    fn into(self) -> JsRequest {
+        let (req, body) = self;
+
+        let mut request: JsRequest = req.into();
+
+        if !body.is_empty() {
+            request.body = Some(body.to_vec());
+        }
+
+        request
+    }
+}
+
+/// Connection-specific headers, meaningless once the response leaves the worker
+const HOP_BY_HOP_HEADERS: [&str; 9] = [
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Most custom reason phrases kept, others fall back to the canonical reason
+const MAX_REASONS: usize = 256;
+
+/// Headers listed in the `connection` headers, hop-by-hop as well
+fn connection_options<'a, I>(headers: I) -> Vec<String>
+where
+    I: Iterator<Item = (&'a str, &'a str)>,
+{
+    headers
+        .filter(|(name, _)| name.eq_ignore_ascii_case("connection"))
+        .flat_map(|(_, value)| value.split(','))
+        .map(|option| option.trim().to_ascii_lowercase())
+        .filter(|option| !option.is_empty())
+        .collect()
+}
+
+/// Whether `name`, lowercase, must not be forwarded
+fn is_hop_by_hop(name: &str, connection: &[String]) -> bool {
+    HOP_BY_HOP_HEADERS.contains(&name) || connection.iter().any(|option| option == name)
+}
+
+/// Request forwarding `req` to `origin`, for events the worker passed through.
+/// The origin is asked for the same path and query, redirects are returned
+/// to the client and the request fails after `timeout`
+pub fn origin_request(
+    req: &HttpRequest,
+    body: Bytes,
+    origin: &str,
+    timeout: Duration,
+) -> FetchRequest {
+    let path = req.uri().path_and_query().map_or("/", |path| path.as_str());
+
+    let connection = connection_options(
+        req.headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?))),
+    );
+
+    let headers = req
+        .headers()
+        .iter()
+        .filter(|(name, _)| name.as_str() != "host" && !is_hop_by_hop(name.as_str(), &connection))
+        // Set by the client from the forwarded body
+        .filter(|(name, _)| **name != CONTENT_LENGTH)
+        .map(|(name, value)| {
+            let value = String::from_utf8_lossy(value.as_bytes()).to_string();
+            (name.to_string(), value)
+        })
+        .collect();
+
+    FetchRequest {
+        url: format!("{}{}", origin.trim_end_matches('/'), path),
+        method: req.method().to_string(),
+        headers,
+        body: if body.is_empty() {
+            None
+        } else {
+            Some(body.to_vec())
+        },
+        redirect: RedirectMode::Manual,
+        timeout: Some(timeout),
+    }
+}
+
+/// Reason phrases must outlive the response head, each distinct custom
+/// phrase is leaked once. `None` when the canonical reason of `status` is used
+fn intern_reason(status: StatusCode, reason: &str) -> Option<&'static str> {
+    static REASONS: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+
+    if reason.is_empty() || status.canonical_reason() == Some(reason) {
+        return None;
+    }
+
+    // reason-phrase = *( HTAB / SP / VCHAR / obs-text )
+    let valid = reason
+        .bytes()
+        .all(|c| c == b'\t' || c == b' ' || (0x21..=0x7e).contains(&c) || c >= 0x80);
+
+    if !valid {
+        println!("Invalid status text: {:?}", reason);
+        return None;
+    }
+
+    let mut reasons = REASONS.get_or_init(Default::default).lock().unwrap();
+
+    if let Some(interned) = reasons.get(reason) {
+        return Some(*interned);
+    }
+
+    if reasons.len() >= MAX_REASONS {
+        println!("Too many status texts, ignoring {:?}", reason);
+        return None;
+    }
+
+    let interned: &'static str = Box::leak(reason.to_string().into_boxed_str());
+    reasons.insert(interned);
+
+    Some(interned)
+}
+
+/// Statuses whose responses never have a body, see "null body status"
+fn is_bodiless(status: StatusCode) -> bool {
+    status.is_informational()
+        || status == StatusCode::NO_CONTENT
+        || status == StatusCode::RESET_CONTENT
+        || status == StatusCode::NOT_MODIFIED
+}
+
+/// Append the headers that may be forwarded to `builder`, returns whether a
+/// content type was given
+fn forward_headers(builder: &mut HttpResponseBuilder, headers: &[(String, String)]) -> bool {
+    let mut has_content_type = false;
+
+    let connection = connection_options(
+        headers
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str())),
+    );
+
+    for (name, value) in headers.iter() {
+        let name = match HeaderName::from_bytes(name.as_bytes()) {
+            Ok(name) => name,
+            Err(_) => {
+                println!("Invalid header name: {:?}", name);
+                continue;
+            }
+        };
+
+        if is_hop_by_hop(name.as_str(), &connection) {
+            continue;
         }
+
+        // End-to-end, but actix computes it from the body it actually writes,
+        // a stale or duplicate length would corrupt the framing
+        if name == CONTENT_LENGTH {
+            continue;
+        }
+
+        let value = match HeaderValue::from_str(value) {
+            Ok(value) => value,
+            Err(_) => {
+                println!("Invalid value for header {}: {:?}", name, value);
+                continue;
+            }
+        };
+
+        has_content_type |= name == CONTENT_TYPE;
+
+        // Repeated headers, e.g. Set-Cookie, are all kept
+        builder.append_header((name, value));
     }
+
+    has_content_type
 }
 
 /// Convert a JsResponse into an Actix response
 impl Into<HttpResponse> for JsResponse {
     fn into(self) -> HttpResponse {
-        let ct = self
-            .headers
-            .get("Content-Type")
-            .unwrap_or(&DEFAULT_CONTENT_TYPE.to_string())
-            .to_string();
-
         let status = self.status;
         let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
 
-        let body = self.body.unwrap_or_default();
+        let mut builder = HttpResponse::build(status);
+
+        let has_content_type = forward_headers(&mut builder, &self.headers);
+
+        if !has_content_type && !is_bodiless(status) {
+            builder.content_type(DEFAULT_CONTENT_TYPE);
+        }
+
+        let reason = self
+            .status_text
+            .as_deref()
+            .and_then(|reason| intern_reason(status, reason));
+
+        let mut response = match self.stream {
+            // Chunks are pulled as actix writes them, dropping the body when
+            // the client disconnects cancels the stream in the worker
+            Some(receiver) => builder.streaming(futures::stream::unfold(
+                receiver,
+                |mut receiver| async move {
+                    let chunk = receiver.recv().await?;
+                    Some((chunk.map(Bytes::from), receiver))
+                },
+            )),
+            None => builder.body(self.body),
+        };
+
+        if let Some(reason) = reason {
+            response.head_mut().reason = Some(reason);
+        }
+
+        response
+    }
+}
+
+/// Convert an origin response into an Actix response, with the canonical
+/// reason of its status and its headers as the origin sent them
+impl Into<HttpResponse> for FetchResponse {
+    fn into(self) -> HttpResponse {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::BAD_GATEWAY);
+
+        let mut builder = HttpResponse::build(status);
+
+        forward_headers(&mut builder, &self.headers);
+
+        builder.body(self.body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::origin_request;
+    use super::FetchResponse;
+    use super::JsRequest;
+    use super::JsResponse;
+
+    use std::time::Duration;
+
+    use actix_web::web::Bytes;
+
+    use actix_web::body::MessageBody;
+    use actix_web::test::TestRequest;
+    use actix_web::HttpResponse;
+
+    #[test]
+    fn request_should_have_absolute_url() {
+        let request = TestRequest::default()
+            .uri("/path?a=1")
+            .insert_header(("host", "example.com"))
+            .to_http_request();
+
+        let request: JsRequest = request.into();
+
+        assert_eq!(request.url, "http://example.com/path?a=1");
+    }
+
+    #[test]
+    fn origin_request_should_keep_path_and_end_to_end_headers() {
+        let request = TestRequest::post()
+            .uri("/path?a=1")
+            .insert_header(("host", "example.com"))
+            .insert_header(("connection", "keep-alive"))
+            .insert_header(("x-custom", "1"))
+            .to_http_request();
+
+        let request = origin_request(
+            &request,
+            Bytes::from("body"),
+            "http://origin:8080/",
+            Duration::from_secs(5),
+        );
+
+        assert_eq!(request.url, "http://origin:8080/path?a=1");
+        assert_eq!(request.method, "POST");
+        assert_eq!(
+            request.headers,
+            vec![(String::from("x-custom"), String::from("1"))]
+        );
+        assert_eq!(request.body.as_deref(), Some(&b"body"[..]));
+        assert_eq!(request.timeout, Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn response_should_keep_headers_and_reason() {
+        let mut response = JsResponse::new(201);
+        response.status_text = Some(String::from("Made"));
+        response.headers = vec![
+            (String::from("set-cookie"), String::from("a=1")),
+            (String::from("set-cookie"), String::from("b=2")),
+            (String::from("connection"), String::from("close")),
+            (String::from("x-custom"), String::from("1")),
+        ];
+
+        let response: HttpResponse = response.into();
+
+        let cookies: Vec<_> = response.headers().get_all("set-cookie").collect();
+
+        assert_eq!(cookies, vec!["a=1", "b=2"]);
+        assert_eq!(response.headers().get("x-custom").unwrap(), "1");
+        assert!(response.headers().get("connection").is_none());
+        assert_eq!(response.head().reason, Some("Made"));
+    }
+
+    #[test]
+    fn origin_response_should_have_canonical_reason() {
+        let response = FetchResponse {
+            status: 404,
+            status_text: String::from("Nothing Here"),
+            headers: vec![],
+            body: b"missing".to_vec(),
+            url: String::from("http://origin/"),
+            redirected: false,
+        };
+
+        let response: HttpResponse = response.into();
+
+        assert_eq!(response.head().reason, None);
+        assert_eq!(response.head().reason(), "Not Found");
+    }
+
+    #[test]
+    fn response_should_drop_connection_options() {
+        let mut response = JsResponse::new(200);
+        response.status_text = Some(String::from("OK"));
+        response.headers = vec![
+            (String::from("connection"), String::from("X-Private, close")),
+            (String::from("x-private"), String::from("1")),
+            (String::from("x-custom"), String::from("1")),
+        ];
+
+        let response: HttpResponse = response.into();
+
+        assert!(response.headers().get("x-private").is_none());
+        assert_eq!(response.headers().get("x-custom").unwrap(), "1");
+        assert_eq!(response.head().reason, None);
+    }
+
+    #[test]
+    fn response_should_drop_content_length() {
+        let mut response = JsResponse::new(200);
+        response.headers = vec![(String::from("content-length"), String::from("100"))];
+        response.body = b"short".to_vec();
+
+        let response: HttpResponse = response.into();
+
+        assert!(response.headers().get("content-length").is_none());
+        assert_eq!(
+            response.into_body().size(),
+            actix_web::body::BodySize::Sized(5)
+        );
+    }
+
+    #[test]
+    fn bodiless_response_should_not_have_content_type() {
+        let response: HttpResponse = JsResponse::new(204).into();
+
+        assert!(response.headers().get("content-type").is_none());
+
+        let response: HttpResponse = JsResponse::new(200).into();
+
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "text/plain; charset=utf-8"
+        );
+    }
+
+    #[test]
+    fn origin_response_should_not_have_default_content_type() {
+        let response = FetchResponse {
+            status: 200,
+            status_text: String::from("OK"),
+            headers: vec![(String::from("content-length"), String::from("4"))],
+            body: b"data".to_vec(),
+            url: String::from("http://origin/"),
+            redirected: false,
+        };
+
+        let response: HttpResponse = response.into();
 
-        HttpResponse::build(status)
-            .content_type(ct)
-            .body(body)
+        assert!(response.headers().get("content-type").is_none());
+        assert!(response.headers().get("content-length").is_none());
     }
 }
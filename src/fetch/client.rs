@@ -0,0 +1,411 @@
+use v8::HandleScope;
+use v8::Local;
+use v8::Value;
+
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::Duration;
+
+use futures::future::LocalBoxFuture;
+
+use super::response::body_to_bytes;
+use crate::core::ops;
+use crate::core::JsStateRef;
+use crate::utils;
+
+/// Maximum number of redirects followed in `follow` mode
+const MAX_REDIRECTS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RedirectMode {
+    Follow,
+    Error,
+    /// The redirect response is returned as is
+    Manual,
+}
+
+impl FromStr for RedirectMode {
+    type Err = FetchError;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode {
+            "follow" => Ok(RedirectMode::Follow),
+            "error" => Ok(RedirectMode::Error),
+            "manual" => Ok(RedirectMode::Manual),
+            _ => Err(FetchError::InvalidRequest(format!(
+                "Invalid redirect mode: {}",
+                mode
+            ))),
+        }
+    }
+}
+
+/// Outbound request made by `fetch()`
+#[derive(Debug, Clone)]
+pub struct FetchRequest {
+    pub url: String,
+    pub method: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Vec<u8>>,
+    pub redirect: RedirectMode,
+    pub timeout: Option<Duration>,
+}
+
+/// Response of an outbound request
+#[derive(Debug, Clone, Default)]
+pub struct FetchResponse {
+    pub status: u16,
+    pub status_text: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// Final URL, after redirects
+    pub url: String,
+    pub redirected: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FetchError {
+    InvalidRequest(String),
+    Network(String),
+    /// A redirect was received in `error` mode, or too many were followed
+    Redirect,
+    Timeout,
+}
+
+impl std::fmt::Display for FetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FetchError::InvalidRequest(message) => write!(f, "Invalid request: {}", message),
+            FetchError::Network(message) => write!(f, "Network error: {}", message),
+            FetchError::Redirect => write!(f, "Unexpected redirect"),
+            FetchError::Timeout => write!(f, "Request timed out"),
+        }
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+/// Performs the outbound requests of a runtime
+pub trait HttpClient {
+    fn fetch(
+        &self,
+        request: FetchRequest,
+    ) -> LocalBoxFuture<'static, Result<FetchResponse, FetchError>>;
+}
+
+/// Default client, backed by reqwest
+pub struct ReqwestClient {
+    follow: reqwest::Client,
+    no_follow: reqwest::Client,
+}
+
+impl Default for ReqwestClient {
+    fn default() -> Self {
+        let policy = reqwest::redirect::Policy::limited(MAX_REDIRECTS);
+
+        ReqwestClient {
+            follow: reqwest::Client::builder().redirect(policy).build().unwrap(),
+            no_follow: reqwest::Client::builder()
+                .redirect(reqwest::redirect::Policy::none())
+                .build()
+                .unwrap(),
+        }
+    }
+}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(err: reqwest::Error) -> Self {
+        match err.is_timeout() {
+            true => FetchError::Timeout,
+            false if err.is_redirect() => FetchError::Redirect,
+            false if err.is_builder() => FetchError::InvalidRequest(err.to_string()),
+            false => FetchError::Network(err.to_string()),
+        }
+    }
+}
+
+impl HttpClient for ReqwestClient {
+    fn fetch(
+        &self,
+        request: FetchRequest,
+    ) -> LocalBoxFuture<'static, Result<FetchResponse, FetchError>> {
+        let client = match request.redirect {
+            RedirectMode::Follow => self.follow.clone(),
+            RedirectMode::Error | RedirectMode::Manual => self.no_follow.clone(),
+        };
+
+        Box::pin(async move {
+            let method = reqwest::Method::from_bytes(request.method.as_bytes()).map_err(|_| {
+                FetchError::InvalidRequest(format!("Invalid method: {}", request.method))
+            })?;
+
+            let requested = reqwest::Url::parse(&request.url)
+                .map_err(|err| FetchError::InvalidRequest(err.to_string()))?;
+
+            let mut builder = client.request(method, requested.clone());
+
+            for (name, value) in request.headers {
+                builder = builder.header(name, value);
+            }
+
+            if let Some(body) = request.body {
+                builder = builder.body(body);
+            }
+
+            if let Some(timeout) = request.timeout {
+                builder = builder.timeout(timeout);
+            }
+
+            let response = builder.send().await?;
+
+            let status = response.status();
+
+            if request.redirect == RedirectMode::Error && status.is_redirection() {
+                return Err(FetchError::Redirect);
+            }
+
+            let headers = response
+                .headers()
+                .iter()
+                .map(|(name, value)| {
+                    let value = String::from_utf8_lossy(value.as_bytes()).to_string();
+                    (name.to_string(), value)
+                })
+                .collect();
+
+            let url = response.url().clone();
+
+            Ok(FetchResponse {
+                status: status.as_u16(),
+                status_text: status.canonical_reason().unwrap_or_default().to_string(),
+                headers,
+                redirected: url != requested,
+                url: url.to_string(),
+                body: response.bytes().await?.to_vec(),
+            })
+        })
+    }
+}
+
+type MockHandler = dyn Fn(&FetchRequest) -> Result<FetchResponse, FetchError>;
+
+/// In-process client for tests, responses are produced by a handler.
+/// Redirects are handled like a real client would
+#[derive(Clone)]
+pub struct MockHttpClient {
+    handler: Rc<MockHandler>,
+    delay: Option<Duration>,
+}
+
+impl MockHttpClient {
+    pub fn new<F>(handler: F) -> Self
+    where
+        F: Fn(&FetchRequest) -> Result<FetchResponse, FetchError> + 'static,
+    {
+        MockHttpClient {
+            handler: Rc::new(handler),
+            delay: None,
+        }
+    }
+
+    /// Wait `delay` before each response, e.g. to test timeouts
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+}
+
+impl HttpClient for MockHttpClient {
+    fn fetch(
+        &self,
+        mut request: FetchRequest,
+    ) -> LocalBoxFuture<'static, Result<FetchResponse, FetchError>> {
+        let client = self.clone();
+
+        Box::pin(async move {
+            if let Some(delay) = client.delay {
+                tokio::time::sleep(delay).await;
+            }
+
+            let requested = request.url.clone();
+
+            for _ in 0..=MAX_REDIRECTS {
+                let mut response = (client.handler)(&request)?;
+
+                let location = response
+                    .headers
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case("location"))
+                    .map(|(_, location)| location.clone());
+
+                let is_redirect = (300..400).contains(&response.status);
+
+                match (is_redirect, location, request.redirect) {
+                    (true, Some(_), RedirectMode::Error) => return Err(FetchError::Redirect),
+                    (true, Some(location), RedirectMode::Follow) => {
+                        let url = url::Url::parse(&request.url)
+                            .and_then(|url| url.join(&location))
+                            .map_err(|err| FetchError::Network(err.to_string()))?;
+
+                        request.url = url.to_string();
+                    }
+                    _ => {
+                        response.redirected = request.url != requested;
+                        response.url = request.url;
+                        return Ok(response);
+                    }
+                }
+            }
+
+            Err(FetchError::Redirect)
+        })
+    }
+}
+
+/// Resolves with the response as a plain object, or rejects with a TypeError
+impl ops::OpResult for Result<FetchResponse, FetchError> {
+    fn into_value<'s>(
+        self: Box<Self>,
+        scope: &mut HandleScope<'s>,
+    ) -> Result<Local<'s, Value>, Local<'s, Value>> {
+        let response = match *self {
+            Ok(response) => response,
+            Err(err) => {
+                let message = v8::String::new(scope, &format!("fetch failed: {}", err)).unwrap();
+                return Err(v8::Exception::type_error(scope, message));
+            }
+        };
+
+        let object = v8::Object::new(scope);
+
+        let headers: Vec<Local<Value>> = response
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                let name = v8::String::new(scope, name).unwrap().into();
+                let value = v8::String::new(scope, value).unwrap().into();
+
+                v8::Array::new_with_elements(scope, &[name, value]).into()
+            })
+            .collect();
+        let headers = v8::Array::new_with_elements(scope, &headers);
+
+        let store = v8::ArrayBuffer::new_backing_store_from_vec(response.body);
+        let body = v8::ArrayBuffer::with_backing_store(scope, &store.make_shared());
+
+        let status = v8::Integer::new(scope, response.status as i32);
+        let redirected = v8::Boolean::new(scope, response.redirected);
+
+        utils::assign(scope, object, "status", status.into());
+        utils::assign_string(scope, object, "statusText", response.status_text);
+        utils::assign(scope, object, "headers", headers.into());
+        utils::assign(scope, object, "body", body.into());
+        utils::assign_string(scope, object, "url", response.url);
+        utils::assign(scope, object, "redirected", redirected.into());
+
+        Ok(object.into())
+    }
+}
+
+fn parse_request(scope: &mut HandleScope, request: Local<Value>) -> Result<FetchRequest, String> {
+    let request = match request.is_object() {
+        true => request.to_object(scope).unwrap(),
+        false => return Err(String::from("Request must be an object")),
+    };
+
+    let url = utils::get(scope, request, "url").to_rust_string_lossy(scope);
+    let method = utils::get(scope, request, "method").to_rust_string_lossy(scope);
+
+    let headers = utils::get(scope, request, "headers");
+    let headers: Local<v8::Array> = headers
+        .try_into()
+        .map_err(|_| String::from("Request headers must be an array"))?;
+
+    let headers = (0..headers.length())
+        .filter_map(|index| {
+            let entry = headers.get_index(scope, index)?;
+            let entry: Local<v8::Array> = entry.try_into().ok()?;
+
+            let name = entry.get_index(scope, 0)?.to_rust_string_lossy(scope);
+            let value = entry.get_index(scope, 1)?.to_rust_string_lossy(scope);
+
+            Some((name, value))
+        })
+        .collect();
+
+    let body = utils::get(scope, request, "body");
+    let body = match body.is_null_or_undefined() {
+        true => None,
+        false => Some(body_to_bytes(scope, body)?),
+    };
+
+    let redirect = utils::get(scope, request, "redirect");
+    let redirect = match redirect.is_undefined() {
+        true => RedirectMode::Follow,
+        false => redirect
+            .to_rust_string_lossy(scope)
+            .parse()
+            .map_err(|err: FetchError| err.to_string())?,
+    };
+
+    let timeout = utils::get(scope, request, "timeout");
+    let timeout = match timeout.is_number() {
+        true => Some(Duration::from_millis(
+            timeout.number_value(scope).unwrap_or(0.0).max(0.0) as u64,
+        )),
+        false => None,
+    };
+
+    Ok(FetchRequest {
+        url,
+        method,
+        headers,
+        body,
+        redirect,
+        timeout,
+    })
+}
+
+/// Native part of `fetch()`, takes a plain request object and returns the op
+/// id, to cancel it, and a promise settled by the event loop
+pub(crate) fn fetch_callback(
+    scope: &mut HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut ret: v8::ReturnValue,
+) {
+    let request = match parse_request(scope, args.get(0)) {
+        Ok(request) => request,
+        Err(message) => {
+            utils::throw_type_error(scope, &message);
+            return;
+        }
+    };
+
+    let client = {
+        let state = scope.get_slot::<JsStateRef>().expect("No state found");
+        let mut state = state.borrow_mut();
+
+        state
+            .http_client
+            .get_or_insert_with(|| Rc::new(ReqwestClient::default()))
+            .clone()
+    };
+
+    let timeout = request.timeout;
+    let response = client.fetch(request);
+
+    let (id, promise) = ops::spawn_cancelable_op(scope, async move {
+        // Applied here so that every client honours it
+        match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, response)
+                .await
+                .unwrap_or(Err(FetchError::Timeout)),
+            None => response.await,
+        }
+    });
+
+    let id = v8::Integer::new_from_unsigned(scope, id).into();
+    let result = v8::Array::new_with_elements(scope, &[id, promise.into()]);
+
+    ret.set(result.into());
+}
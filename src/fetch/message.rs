@@ -6,33 +6,126 @@ use tokio::sync::oneshot;
 use tokio::sync::oneshot::Receiver;
 use tokio::sync::oneshot::Sender;
 
+use super::stream;
 use super::JsRequest;
 use super::JsResponse;
+use crate::core::RuntimeBasicMessage;
 use crate::core::RuntimeMessage;
 use crate::utils;
 
+/// Why a fetch event ended without a response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoResponse {
+    /// No listener called `event.respondWith` during the dispatch
+    NotHandled,
+    /// A listener failed after calling `event.passThroughOnException`, the
+    /// request should be forwarded to the origin
+    PassThrough,
+    /// The promise given to `event.respondWith` never settled
+    Unsettled,
+    /// The worker did not respond before the host gave up on it
+    TimedOut,
+}
+
+impl std::fmt::Display for NoResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            NoResponse::NotHandled => write!(f, "No fetch listener called respondWith"),
+            NoResponse::PassThrough => write!(f, "Fetch listener failed, passing through"),
+            NoResponse::Unsettled => write!(f, "The promise given to respondWith never settled"),
+            NoResponse::TimedOut => write!(f, "The worker did not respond in time"),
+        }
+    }
+}
+
+type FetchSender = Sender<Result<JsResponse, NoResponse>>;
+
+/// Told once the fetch event settled, see `settled`
+struct SettledSender(Sender<()>);
+
 pub struct RuntimeFetchMessage {
     request: JsRequest,
-    tx: Option<Sender<JsResponse>>,
-    rx: Option<Receiver<JsResponse>>,
+    tx: Option<FetchSender>,
+    rx: Option<Receiver<Result<JsResponse, NoResponse>>>,
+    no_response: Option<NoResponse>,
+    settled_tx: Option<SettledSender>,
+    settled_rx: Option<Receiver<()>>,
 }
 
 impl RuntimeFetchMessage {
     pub fn new(request: JsRequest) -> Self {
         let (sender, receiver) = oneshot::channel();
+        let (settled_sender, settled_receiver) = oneshot::channel();
 
         RuntimeFetchMessage {
             request,
             tx: Some(sender),
             rx: Some(receiver),
+            no_response: None,
+            settled_tx: Some(SettledSender(settled_sender)),
+            settled_rx: Some(settled_receiver),
+        }
+    }
+
+    /// Response if it was already sent, without waiting for it
+    pub fn try_get_response(&mut self) -> Option<JsResponse> {
+        match self.rx.as_mut()?.try_recv() {
+            Ok(Ok(response)) => Some(response),
+            Ok(Err(reason)) => {
+                self.no_response = Some(reason);
+                None
+            }
+            Err(_) => None,
+        }
+    }
+
+    /// Why the worker did not respond, to be called once the event loop is
+    /// drained: a pending `respondWith` promise cannot settle anymore
+    pub fn no_response(&mut self) -> Option<NoResponse> {
+        if self.no_response.is_some() {
+            return self.no_response;
         }
+
+        match self.rx.as_mut()?.try_recv() {
+            Ok(Ok(_)) => None,
+            Ok(Err(reason)) => Some(reason),
+            Err(oneshot::error::TryRecvError::Empty) => Some(NoResponse::Unsettled),
+            Err(oneshot::error::TryRecvError::Closed) => None,
+        }
+    }
+
+    /// Wait until the response was sent and the `waitUntil` promises of the
+    /// event settled, or until the worker cannot tell anymore. Can be
+    /// canceled and called again
+    pub async fn settled(&mut self) {
+        if let Some(receiver) = self.settled_rx.as_mut() {
+            receiver.await.ok();
+            self.settled_rx = None;
+        }
+    }
+
+    /// Message aborting `request.signal` of the event being handled, once
+    /// the client is gone
+    pub fn abort_message() -> RuntimeBasicMessage {
+        RuntimeBasicMessage::new(String::from("abort"))
     }
 
+    /// Wait for the response, `None` if the worker cannot respond anymore
+    /// (see `no_response`). Can be canceled (e.g. in a select) and called again
     pub async fn get_response(&mut self) -> Option<JsResponse> {
         println!("Waiting for response...");
-        let receiver = self.rx.take().unwrap();
+        let receiver = self.rx.as_mut()?;
 
-        let response = receiver.await.unwrap();
+        let response = receiver.await;
+        self.rx = None;
+
+        let response = match response.ok()? {
+            Ok(response) => response,
+            Err(reason) => {
+                self.no_response = Some(reason);
+                return None;
+            }
+        };
 
         println!("Got response: {:?}", response);
 
@@ -40,11 +133,16 @@ impl RuntimeFetchMessage {
     }
 }
 
+/// Sender of the fetch message being handled, `None` once it was used
+fn take_sender(scope: &mut HandleScope) -> Option<FetchSender> {
+    scope.get_slot_mut::<Option<FetchSender>>()?.take()
+}
+
 /// Callback for event.respondWith
 fn respond_with_callback<'a>(
     scope: &mut HandleScope<'a>,
     args: v8::FunctionCallbackArguments<'a>,
-    _ret: v8::ReturnValue,
+    mut ret: v8::ReturnValue,
 ) {
     let js_response: Local<'_, Value> = args.get(0);
 
@@ -53,31 +151,85 @@ fn respond_with_callback<'a>(
         utils::inspect_v8_value(js_response, scope)
     );
 
-    let response = JsResponse::from_v8_value(scope, js_response);
+    let mut response = JsResponse::from_v8_value(scope, js_response);
 
-    // Get sender from state
-    let state = scope.get_slot_mut::<Option<Sender<JsResponse>>>();
-    let sender = match state {
-        Some(sender) => sender.take().unwrap(),
+    // The body is written with writeStream under the returned stream id
+    if let Ok(response) = response.as_mut() {
+        let js_response = js_response.to_object(scope).unwrap();
+
+        if utils::get(scope, js_response, "stream").boolean_value(scope) {
+            let (id, receiver) = stream::open_stream(scope);
+
+            response.stream = Some(receiver);
+            ret.set(v8::Integer::new_from_unsigned(scope, id).into());
+        }
+    }
+
+    let sender = match take_sender(scope) {
+        Some(sender) => sender,
         None => {
-            println!("No state found!!");
+            utils::throw_type_error(scope, "Already responded to this fetch event");
             return;
         }
     };
 
-    // Send response
+    // Send response, the host may have stopped waiting for it
     match response {
-        Some(response) => {
+        Ok(response) => {
             println!("Sending response: {:?}", response);
-            sender.send(response).unwrap();
+            sender.send(Ok(response)).ok();
         }
-        None => {
-            println!("Error creating response: {:?}", response);
-            sender.send(JsResponse::new(500)).unwrap();
+        Err(message) => {
+            println!("Error creating response: {}", message);
+            sender.send(Ok(JsResponse::new(500))).ok();
+
+            utils::throw_type_error(scope, &message);
         }
     }
 }
 
+/// Callback for events ending without a response, with the reason:
+/// "not-handled" or "pass-through"
+fn no_response_callback<'a>(
+    scope: &mut HandleScope<'a>,
+    args: v8::FunctionCallbackArguments<'a>,
+    _ret: v8::ReturnValue,
+) {
+    let reason = match args.get(0).to_rust_string_lossy(scope).as_str() {
+        "not-handled" => NoResponse::NotHandled,
+        "pass-through" => NoResponse::PassThrough,
+        reason => {
+            let message = format!("Unknown reason: \"{}\"", reason);
+            utils::throw_type_error(scope, &message);
+            return;
+        }
+    };
+
+    match take_sender(scope) {
+        Some(sender) => {
+            println!("No response: {}", reason);
+            sender.send(Err(reason)).ok();
+        }
+        None => utils::throw_type_error(scope, "Already responded to this fetch event"),
+    }
+}
+
+/// Callback for the end of the fetch event: the response was sent and the
+/// `waitUntil` promises settled
+fn settled_callback<'a>(
+    scope: &mut HandleScope<'a>,
+    _args: v8::FunctionCallbackArguments<'a>,
+    _ret: v8::ReturnValue,
+) {
+    let sender = scope
+        .get_slot_mut::<Option<SettledSender>>()
+        .and_then(|sender| sender.take());
+
+    if let Some(SettledSender(sender)) = sender {
+        sender.send(()).ok();
+    }
+}
+
 impl RuntimeMessage for RuntimeFetchMessage {
     fn kind(&self) -> String {
         "fetch".to_string()
@@ -87,6 +239,7 @@ impl RuntimeMessage for RuntimeFetchMessage {
         let sender = self.tx.take();
 
         scope.set_slot(sender);
+        scope.set_slot(self.settled_tx.take());
     }
 
     fn to_value<'s>(&self, scope: &mut HandleScope<'s>) -> Local<'s, Value> {
@@ -103,6 +256,18 @@ impl RuntimeMessage for RuntimeFetchMessage {
         let respond_with = v8::Function::new(scope, respond_with_callback).unwrap();
         utils::assign(scope, event, "sendResponse", respond_with.into());
 
+        let no_response = v8::Function::new(scope, no_response_callback).unwrap();
+        utils::assign(scope, event, "sendNoResponse", no_response.into());
+
+        let settled = v8::Function::new(scope, settled_callback).unwrap();
+        utils::assign(scope, event, "sendSettled", settled.into());
+
+        let write_stream = v8::Function::new(scope, stream::write_stream_callback).unwrap();
+        utils::assign(scope, event, "writeStream", write_stream.into());
+
+        let close_stream = v8::Function::new(scope, stream::close_stream_callback).unwrap();
+        utils::assign(scope, event, "closeStream", close_stream.into());
+
         event.into()
     }
 }
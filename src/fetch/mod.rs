@@ -1,8 +1,19 @@
 pub mod actix;
+pub mod client;
 pub mod message;
+pub mod multipart;
 pub mod request;
 pub mod response;
+pub mod stream;
 
+pub use client::FetchError;
+pub use client::FetchRequest;
+pub use client::FetchResponse;
+pub use client::HttpClient;
+pub use client::MockHttpClient;
+pub use client::RedirectMode;
+pub use client::ReqwestClient;
+pub use message::NoResponse;
 pub use message::RuntimeFetchMessage;
 pub use request::JsRequest;
 pub use response::JsResponse;
@@ -0,0 +1,245 @@
+use v8::HandleScope;
+use v8::Local;
+
+use super::response::body_to_bytes;
+use crate::utils;
+
+/// Part of a multipart/form-data body
+#[derive(Debug, PartialEq)]
+pub struct Part {
+    pub name: String,
+    /// Set for file entries
+    pub filename: Option<String>,
+    pub content_type: Option<String>,
+    pub data: Vec<u8>,
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|index| index + from)
+}
+
+/// Parameters of a Content-Disposition value, quoted values are unescaped
+fn disposition_params(value: &str) -> Vec<(String, String)> {
+    let mut params = vec![];
+    let mut chars = value.chars().peekable();
+
+    // Skip the disposition type
+    for c in chars.by_ref() {
+        if c == ';' {
+            break;
+        }
+    }
+
+    loop {
+        let name: String = chars
+            .by_ref()
+            .skip_while(|c| c.is_whitespace())
+            .take_while(|c| *c != '=')
+            .collect();
+
+        if name.is_empty() {
+            return params;
+        }
+
+        let mut value = String::new();
+
+        if chars.peek() == Some(&'"') {
+            chars.next();
+
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => value.extend(chars.next()),
+                    '"' => break,
+                    c => value.push(c),
+                }
+            }
+
+            // Skip to the next parameter
+            for c in chars.by_ref() {
+                if c == ';' {
+                    break;
+                }
+            }
+        } else {
+            value = chars.by_ref().take_while(|c| *c != ';').collect();
+        }
+
+        params.push((name.trim().to_lowercase(), value.trim().to_string()));
+    }
+}
+
+/// Split a multipart/form-data body into its parts
+pub fn parse(body: &[u8], boundary: &str) -> Result<Vec<Part>, String> {
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let close_delimiter = [&b"\r\n"[..], &delimiter].concat();
+
+    let mut parts = vec![];
+
+    let mut pos = find(body, &delimiter, 0).ok_or("Missing multipart boundary")? + delimiter.len();
+
+    loop {
+        if body[pos..].starts_with(b"--") {
+            return Ok(parts);
+        }
+
+        // Transport padding, then the end of the delimiter line
+        while matches!(body.get(pos), Some(b' ' | b'\t')) {
+            pos += 1;
+        }
+
+        if !body[pos..].starts_with(b"\r\n") {
+            return Err(String::from("Invalid multipart delimiter"));
+        }
+
+        pos += 2;
+
+        let (headers, content_start) = match body[pos..].starts_with(b"\r\n") {
+            true => ("", pos + 2),
+            false => {
+                let end = find(body, b"\r\n\r\n", pos).ok_or("Invalid multipart headers")?;
+                let headers = std::str::from_utf8(&body[pos..end])
+                    .map_err(|_| String::from("Invalid multipart headers"))?;
+
+                (headers, end + 4)
+            }
+        };
+
+        let mut name = None;
+        let mut filename = None;
+        let mut content_type = None;
+
+        for line in headers.split("\r\n") {
+            let (header, value) = match line.split_once(':') {
+                Some((header, value)) => (header.trim().to_lowercase(), value.trim()),
+                None => continue,
+            };
+
+            match header.as_str() {
+                "content-disposition" => {
+                    for (param, value) in disposition_params(value) {
+                        match param.as_str() {
+                            "name" => name = Some(value),
+                            "filename" => filename = Some(value),
+                            _ => {}
+                        }
+                    }
+                }
+                "content-type" => content_type = Some(value.to_string()),
+                _ => {}
+            }
+        }
+
+        let end = find(body, &close_delimiter, content_start)
+            .ok_or("Missing closing multipart boundary")?;
+
+        parts.push(Part {
+            name: name.ok_or("Multipart part without name")?,
+            filename,
+            content_type,
+            data: body[content_start..end].to_vec(),
+        });
+
+        pos = end + close_delimiter.len();
+    }
+}
+
+/// __parseMultipart(body, boundary): entries of a multipart/form-data body,
+/// `{ name, value }` for fields and `{ name, filename, type, data }` for files
+pub(crate) fn parse_multipart_callback(
+    scope: &mut HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut ret: v8::ReturnValue,
+) {
+    let boundary = args.get(1).to_rust_string_lossy(scope);
+
+    let parts = match body_to_bytes(scope, args.get(0)).and_then(|body| parse(&body, &boundary)) {
+        Ok(parts) => parts,
+        Err(message) => {
+            utils::throw_type_error(scope, &message);
+            return;
+        }
+    };
+
+    let entries: Vec<Local<v8::Value>> = parts
+        .into_iter()
+        .map(|part| {
+            let entry = v8::Object::new(scope);
+
+            utils::assign_string(scope, entry, "name", part.name);
+
+            match part.filename {
+                Some(filename) => {
+                    let store = v8::ArrayBuffer::new_backing_store_from_vec(part.data);
+                    let data = v8::ArrayBuffer::with_backing_store(scope, &store.make_shared());
+
+                    utils::assign_string(scope, entry, "filename", filename);
+                    utils::assign_string(
+                        scope,
+                        entry,
+                        "type",
+                        part.content_type.unwrap_or_default(),
+                    );
+                    utils::assign(scope, entry, "data", data.into());
+                }
+                None => {
+                    let value = String::from_utf8_lossy(&part.data).to_string();
+                    utils::assign_string(scope, entry, "value", value);
+                }
+            }
+
+            entry.into()
+        })
+        .collect();
+
+    ret.set(v8::Array::new_with_elements(scope, &entries).into());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+    use super::Part;
+
+    #[test]
+    fn multipart_should_parse_fields_and_files() {
+        let body = b"preamble\r\n--xyz\r\n\
+            Content-Disposition: form-data; name=\"field\"\r\n\r\n\
+            a value\r\n\
+            --xyz\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"a \\\"b\\\".txt\"\r\n\
+            Content-Type: text/plain\r\n\r\n\
+            line 1\r\nline 2\r\n\
+            --xyz--\r\n";
+
+        let parts = parse(body, "xyz").unwrap();
+
+        assert_eq!(
+            parts,
+            vec![
+                Part {
+                    name: String::from("field"),
+                    filename: None,
+                    content_type: None,
+                    data: b"a value".to_vec(),
+                },
+                Part {
+                    name: String::from("file"),
+                    filename: Some(String::from("a \"b\".txt")),
+                    content_type: Some(String::from("text/plain")),
+                    data: b"line 1\r\nline 2".to_vec(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn multipart_should_reject_unterminated_body() {
+        let body = b"--xyz\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nvalue";
+
+        assert!(parse(body, "xyz").is_err());
+        assert!(parse(b"no boundary", "xyz").is_err());
+    }
+}
@@ -8,20 +8,49 @@ use crate::utils;
 pub struct JsRequest {
     pub url: String,
     pub method: String,
+    /// Header names and values in received order, repeated headers are kept
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Vec<u8>>,
 }
 
 impl JsRequest {
     pub fn new(url: String, method: String) -> JsRequest {
-        JsRequest { url, method }
+        JsRequest {
+            url,
+            method,
+            headers: vec![],
+            body: None,
+        }
     }
 
     pub fn to_value<'s>(&self, scope: &mut HandleScope<'s>) -> Local<'s, Value> {
         let request = Object::new(scope);
-        let headers = v8::Map::new(scope);
+
+        // Array of [name, value] pairs, accepted by the Headers constructor
+        let headers: Vec<Local<Value>> = self
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                let name = v8::String::new(scope, name).unwrap().into();
+                let value = v8::String::new(scope, value).unwrap().into();
+
+                v8::Array::new_with_elements(scope, &[name, value]).into()
+            })
+            .collect();
+        let headers = v8::Array::new_with_elements(scope, &headers);
+
+        let body: Local<Value> = match &self.body {
+            Some(body) => {
+                let store = v8::ArrayBuffer::new_backing_store_from_vec(body.clone());
+                v8::ArrayBuffer::with_backing_store(scope, &store.make_shared()).into()
+            }
+            None => v8::null(scope).into(),
+        };
 
         utils::assign_string(scope, request, "url", self.url.clone());
         utils::assign_string(scope, request, "method", self.method.clone());
         utils::assign(scope, request, "headers", headers.into());
+        utils::assign(scope, request, "body", body);
 
         request.into()
     }
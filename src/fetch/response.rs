@@ -1,51 +1,83 @@
-use std::collections::HashMap;
-
 use v8::HandleScope;
 use v8::Local;
 use v8::Object;
 use v8::Value;
 
+use tokio::sync::mpsc;
+
+use super::stream::BodyChunk;
 use crate::utils;
 use crate::utils::inspect::inspect_v8_value;
 
 #[derive(Debug)]
 pub struct JsResponse {
     pub status: u16,
-    pub body: Option<String>,
-    pub headers: HashMap<String, String>,
+    pub status_text: Option<String>,
+    pub body: Vec<u8>,
+    /// Header names and values, repeated headers are kept
+    pub headers: Vec<(String, String)>,
+    /// Chunks of a streamed body, sent while the event loop runs. `body` is
+    /// empty when set
+    pub stream: Option<mpsc::Receiver<BodyChunk>>,
 }
 
 impl<'a> JsResponse {
     pub fn new(status: u16) -> Self {
         JsResponse {
             status,
-            body: None,
-            headers: HashMap::new(),
+            status_text: None,
+            body: vec![],
+            headers: vec![],
+            stream: None,
         }
     }
 
+    /// Receive a streamed body into `body`, the event loop must be running
+    /// concurrently for the chunks to be produced
+    pub async fn collect_body(&mut self) -> Result<(), String> {
+        if let Some(mut stream) = self.stream.take() {
+            while let Some(chunk) = stream.recv().await {
+                self.body.extend(chunk?);
+            }
+        }
+
+        Ok(())
+    }
+
     pub(super) fn from_v8_value(
         scope: &mut HandleScope<'a>,
         response: Local<'a, Value>,
-    ) -> Option<Self> {
+    ) -> Result<Self, String> {
         println!("from_v8_value {}", inspect_v8_value(response, scope));
 
-        let mut res = JsResponse {
-            status: 200,
-            body: None,
-            headers: HashMap::new(),
-        };
+        let mut res = JsResponse::new(200);
 
-        let response: Local<Object> = response.to_object(scope).unwrap();
+        let response: Local<Object> = match response.is_object() {
+            true => response.to_object(scope).unwrap(),
+            false => return Err(String::from("Response must be an object")),
+        };
 
         // Status
         {
             let status_key = utils::v8_str_static!(scope, b"status");
             res.status = match response.get(scope, status_key.into()) {
-                Some(status) if status.is_number() => {
-                    status.to_uint32(scope)?.value().try_into().ok()?
+                Some(status) if status.is_number() => status
+                    .uint32_value(scope)
+                    .and_then(|status| status.try_into().ok())
+                    .filter(|status| (200..=599).contains(status))
+                    .ok_or_else(|| String::from("Invalid response status"))?,
+                _ => return Err(String::from("Response status must be a number")),
+            };
+        }
+
+        // Status text
+        {
+            let status_text_key = utils::v8_str_static!(scope, b"statusText");
+            res.status_text = match response.get(scope, status_text_key.into()) {
+                Some(status_text) if status_text.is_string() => {
+                    Some(status_text.to_rust_string_lossy(scope))
                 }
-                _ => return None,
+                _ => None,
             };
         }
 
@@ -54,34 +86,92 @@ impl<'a> JsResponse {
             let body_key = utils::v8_str_static!(scope, b"body");
             let body = response
                 .get(scope, body_key.into())
-                .unwrap()
-                .to_rust_string_lossy(scope);
+                .unwrap_or_else(|| v8::undefined(scope).into());
 
-            res.body = Some(body);
+            res.body = body_to_bytes(scope, body)?;
         }
 
         // Headers
         {
             let headers_key = utils::v8_str_static!(scope, b"headers");
             let headers = match response.get(scope, headers_key.into()) {
-                Some(headers) if headers.is_object() => headers.to_object(scope)?,
-                _ => return None,
+                Some(headers) if headers.is_object() => headers.to_object(scope).unwrap(),
+                _ => return Err(String::from("Response headers must be an object")),
             };
 
-            let headers_keys = headers
-                .get_own_property_names(scope, v8::GetPropertyNamesArgs::default())
-                .unwrap();
+            // Array of [name, value] pairs, or a plain object. Names are
+            // lowercased like in Headers
+            match v8::Local::<v8::Array>::try_from(headers) {
+                Ok(entries) => {
+                    for index in 0..entries.length() {
+                        let entry = entries
+                            .get_index(scope, index)
+                            .and_then(|entry| v8::Local::<v8::Array>::try_from(entry).ok())
+                            .ok_or_else(|| String::from("Header entries must be arrays"))?;
+
+                        let key = entry.get_index(scope, 0).unwrap();
+                        let val = entry.get_index(scope, 1).unwrap();
+                        let key = key.to_rust_string_lossy(scope).to_lowercase();
+                        let val = val.to_rust_string_lossy(scope);
 
-            for key in 0..headers_keys.length() {
-                let key = headers_keys.get_index(scope, key)?;
-                let val = headers.get(scope, key.into())?;
-                let key = key.to_rust_string_lossy(scope);
-                let val = val.to_rust_string_lossy(scope);
+                        res.headers.push((key, val));
+                    }
+                }
+                Err(_) => {
+                    let headers_keys = headers
+                        .get_own_property_names(scope, v8::GetPropertyNamesArgs::default())
+                        .unwrap();
+
+                    for key in 0..headers_keys.length() {
+                        let key = headers_keys.get_index(scope, key).unwrap();
+                        let val = headers.get(scope, key).unwrap();
+                        let key = key.to_rust_string_lossy(scope).to_lowercase();
+                        let val = val.to_rust_string_lossy(scope);
 
-                res.headers.insert(key, val);
+                        res.headers.push((key, val));
+                    }
+                }
             }
         }
 
-        Some(res)
+        Ok(res)
+    }
+}
+
+/// Bytes of a response body, strings are UTF-8 encoded and null or
+/// undefined give an empty body
+pub(super) fn body_to_bytes(
+    scope: &mut HandleScope,
+    body: Local<Value>,
+) -> Result<Vec<u8>, String> {
+    if body.is_null_or_undefined() {
+        return Ok(vec![]);
+    }
+
+    if body.is_string() {
+        return Ok(body.to_rust_string_lossy(scope).into_bytes());
+    }
+
+    // Typed arrays and DataView
+    if let Ok(view) = Local::<v8::ArrayBufferView>::try_from(body) {
+        let mut bytes = vec![0; view.byte_length()];
+        view.copy_contents(&mut bytes);
+        return Ok(bytes);
     }
+
+    if let Ok(buffer) = Local::<v8::ArrayBuffer>::try_from(body) {
+        let store = buffer.get_backing_store();
+        let bytes = store[..buffer.byte_length()]
+            .iter()
+            .map(|byte| byte.get())
+            .collect();
+        return Ok(bytes);
+    }
+
+    let kind = body.type_of(scope).to_rust_string_lossy(scope);
+
+    Err(format!(
+        "Unsupported response body type ({}), expected a string, ArrayBuffer, ArrayBufferView, null or undefined",
+        kind
+    ))
 }
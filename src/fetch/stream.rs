@@ -0,0 +1,129 @@
+use v8::HandleScope;
+
+use std::collections::HashMap;
+
+use tokio::sync::mpsc;
+
+use super::response::body_to_bytes;
+use crate::core::ops;
+use crate::utils;
+
+/// Chunk of a streamed body, `Err` aborts the response
+pub type BodyChunk = Result<Vec<u8>, String>;
+
+/// Chunks buffered before writes from the worker have to wait
+const STREAM_CAPACITY: usize = 4;
+
+/// Senders of the response bodies being streamed, by stream id
+#[derive(Default)]
+struct ResponseStreams {
+    next_id: u32,
+    senders: HashMap<u32, mpsc::Sender<BodyChunk>>,
+}
+
+fn streams<'a>(scope: &'a mut HandleScope) -> &'a mut ResponseStreams {
+    if scope.get_slot::<ResponseStreams>().is_none() {
+        scope.set_slot(ResponseStreams::default());
+    }
+
+    scope.get_slot_mut::<ResponseStreams>().unwrap()
+}
+
+/// Open a body stream, chunks written by the worker under the returned id
+/// are received by the host
+pub(super) fn open_stream(scope: &mut HandleScope) -> (u32, mpsc::Receiver<BodyChunk>) {
+    let (sender, receiver) = mpsc::channel(STREAM_CAPACITY);
+
+    let streams = streams(scope);
+    streams.next_id += 1;
+    streams.senders.insert(streams.next_id, sender);
+
+    (streams.next_id, receiver)
+}
+
+/// writeStream(id, chunk): the returned promise resolves once the host has
+/// room for the chunk, and rejects if the receiver is gone (e.g. the client
+/// disconnected)
+pub(super) fn write_stream_callback(
+    scope: &mut HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut ret: v8::ReturnValue,
+) {
+    let id = args.get(0).uint32_value(scope).unwrap_or(0);
+
+    let chunk = match body_to_bytes(scope, args.get(1)) {
+        Ok(chunk) => chunk,
+        Err(message) => {
+            utils::throw_type_error(scope, &message);
+            return;
+        }
+    };
+
+    let sender = match streams(scope).senders.get(&id) {
+        Some(sender) => sender.clone(),
+        None => {
+            utils::throw_type_error(scope, "Stream is closed");
+            return;
+        }
+    };
+
+    let promise = ops::spawn_op(scope, async move {
+        sender
+            .send(Ok(chunk))
+            .await
+            .map_err(|_| String::from("Response stream was canceled"))
+    });
+
+    ret.set(promise.into());
+}
+
+/// closeStream(id, error?): end the body, or abort it when an error is given
+pub(super) fn close_stream_callback(
+    scope: &mut HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut ret: v8::ReturnValue,
+) {
+    let id = args.get(0).uint32_value(scope).unwrap_or(0);
+
+    let sender = match streams(scope).senders.remove(&id) {
+        Some(sender) => sender,
+        None => return,
+    };
+
+    let error = args.get(1);
+
+    // Dropping the sender ends the body
+    if error.is_null_or_undefined() {
+        return;
+    }
+
+    let error = error.to_rust_string_lossy(scope);
+
+    let promise = ops::spawn_op(scope, async move {
+        // The receiver may already be gone, there is nobody left to notify
+        sender.send(Err(error)).await.ok();
+        Ok::<(), String>(())
+    });
+
+    ret.set(promise.into());
+}
+
+/// Abort the bodies still being streamed with `error`, e.g. once the work of
+/// the request is dropped, so that clients do not wait for the rest
+pub(crate) fn abort_streams(isolate: &mut v8::Isolate, error: &str) {
+    let streams = match isolate.get_slot_mut::<ResponseStreams>() {
+        Some(streams) => streams,
+        None => return,
+    };
+
+    for (id, sender) in streams.senders.drain() {
+        println!("Aborting response stream {}: {}", id, error);
+
+        let error = error.to_string();
+
+        // Waits for room in the buffer, or for the receiver to be dropped
+        tokio::spawn(async move {
+            sender.send(Err(error)).await.ok();
+        });
+    }
+}
@@ -1,3 +1,4 @@
 pub mod core;
 pub mod fetch;
+pub mod pool;
 pub mod utils;
@@ -0,0 +1,200 @@
+use std::future::Future;
+use std::sync::Arc;
+
+use tokio::sync::mpsc;
+use tokio::sync::Mutex;
+
+use crate::core::JsRuntime;
+
+pub struct PoolOptions {
+    /// Number of runtimes, each on its own thread: isolates must be dropped
+    /// in reverse creation order on a thread, and a busy runtime must not
+    /// stall others
+    pub isolates: usize,
+    /// Number of jobs waiting for an idle runtime before dispatch fails
+    pub queue_size: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum DispatchError {
+    QueueFull,
+    Closed,
+}
+
+impl std::fmt::Display for DispatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for DispatchError {}
+
+/// Runtimes pinned to their own threads, each job is handled by the first
+/// idle runtime
+pub struct RuntimePool<J> {
+    sender: mpsc::Sender<J>,
+}
+
+impl<J: Send + 'static> RuntimePool<J> {
+    /// Spawn the pool threads, `create` builds a runtime on its thread and
+    /// `handle` processes a job, returning the runtime to use for the next
+    /// one (e.g. a new runtime if the previous one had to be recycled)
+    pub fn new<C, CF, H, HF>(options: PoolOptions, create: C, handle: H) -> Self
+    where
+        C: Fn() -> CF + Send + Sync + 'static,
+        CF: Future<Output = JsRuntime>,
+        H: Fn(usize, JsRuntime, J) -> HF + Send + Sync + 'static,
+        HF: Future<Output = JsRuntime>,
+    {
+        let (sender, receiver) = mpsc::channel(options.queue_size.max(1));
+
+        let receiver = Arc::new(Mutex::new(receiver));
+        let create = Arc::new(create);
+        let handle = Arc::new(handle);
+
+        for id in 0..options.isolates.max(1) {
+            let receiver = receiver.clone();
+            let create = create.clone();
+            let handle = handle.clone();
+
+            std::thread::spawn(move || {
+                let rt = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .unwrap();
+
+                let local = tokio::task::LocalSet::new();
+
+                local.spawn_local(async move {
+                    let mut runtime = create().await;
+
+                    println!("Runtime {} ready", id);
+
+                    loop {
+                        // Only idle runtimes wait for a job
+                        let job = receiver.lock().await.recv().await;
+
+                        match job {
+                            Some(job) => runtime = handle(id, runtime, job).await,
+                            None => break,
+                        }
+                    }
+                });
+
+                // Runs until the runtime is done, i.e. the pool is dropped
+                rt.block_on(local);
+            });
+        }
+
+        RuntimePool { sender }
+    }
+
+    /// Queue a job, fails when every runtime is busy and the queue is full
+    pub fn dispatch(&self, job: J) -> Result<(), DispatchError> {
+        self.sender.try_send(job).map_err(|err| match err {
+            mpsc::error::TrySendError::Full(_) => DispatchError::QueueFull,
+            mpsc::error::TrySendError::Closed(_) => DispatchError::Closed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DispatchError;
+    use super::PoolOptions;
+    use super::RuntimePool;
+
+    use crate::core::JsRuntime;
+
+    use std::time::Duration;
+
+    use tokio::sync::oneshot;
+
+    type Job = (String, oneshot::Sender<String>);
+
+    #[tokio::test]
+    async fn pool_should_run_jobs_on_isolates() {
+        let options = PoolOptions {
+            isolates: 4,
+            queue_size: 8,
+        };
+
+        let pool = RuntimePool::new(
+            options,
+            || async { JsRuntime::create_init(None) },
+            |_id, mut rt: JsRuntime, (script, sender): Job| async move {
+                sender.send(rt.eval(&script).unwrap()).unwrap();
+                rt
+            },
+        );
+
+        let mut receivers = vec![];
+
+        for i in 0..4 {
+            let (sender, receiver) = oneshot::channel();
+            pool.dispatch((format!("{} * 2", i), sender)).unwrap();
+            receivers.push(receiver);
+        }
+
+        for (i, receiver) in receivers.into_iter().enumerate() {
+            assert_eq!(receiver.await.unwrap(), format!("{}", i * 2));
+        }
+    }
+
+    #[tokio::test]
+    async fn pool_should_recycle_runtimes() {
+        let options = PoolOptions {
+            isolates: 2,
+            queue_size: 8,
+        };
+
+        // Every job replaces the runtime of its thread
+        let pool = RuntimePool::new(
+            options,
+            || async { JsRuntime::create_init(None) },
+            |_id, rt: JsRuntime, (script, sender): Job| async move {
+                drop(rt);
+
+                let mut rt = JsRuntime::create_init(None);
+                sender.send(rt.eval(&script).unwrap()).unwrap();
+                rt
+            },
+        );
+
+        let mut receivers = vec![];
+
+        for i in 0..6 {
+            let (sender, receiver) = oneshot::channel();
+            pool.dispatch((format!("{} + 1", i), sender)).unwrap();
+            receivers.push(receiver);
+        }
+
+        for (i, receiver) in receivers.into_iter().enumerate() {
+            assert_eq!(receiver.await.unwrap(), format!("{}", i + 1));
+        }
+    }
+
+    #[tokio::test]
+    async fn pool_should_reject_when_queue_is_full() {
+        let options = PoolOptions {
+            isolates: 1,
+            queue_size: 1,
+        };
+
+        let pool = RuntimePool::new(
+            options,
+            || async { JsRuntime::create_init(None) },
+            |_id, rt: JsRuntime, (_script, sender): Job| async move {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                sender.send(String::new()).ok();
+                rt
+            },
+        );
+
+        let results: Vec<_> = (0..3)
+            .map(|_| pool.dispatch((String::new(), oneshot::channel().0)))
+            .collect();
+
+        assert!(results.contains(&Err(DispatchError::QueueFull)));
+    }
+}
@@ -0,0 +1,204 @@
+// Only the runtime creates signals, `new AbortSignal()` throws
+const ABORT_SIGNAL_KEY = Symbol("AbortSignal");
+
+// Set from the AbortSignal class body, `createAbortSignal` makes a signal
+// that is not aborted and `signalAbort` aborts it
+let createAbortSignal;
+let signalAbort;
+
+class AbortSignal extends EventTarget {
+  #aborted = false;
+  #reason = undefined;
+  #onabort = null;
+  // Signals made by AbortSignal.any, aborted along with this one. They are
+  // held weakly so that long-lived signals do not keep them alive
+  #dependents = new Set();
+  #pruneAt = 16;
+  // Dependents with abort listeners, which must live as long as this signal
+  #listened = new Set();
+  // Signals this one was made from by AbortSignal.any
+  #sources = [];
+
+  static {
+    createAbortSignal = () => new AbortSignal(ABORT_SIGNAL_KEY);
+    signalAbort = (signal, reason) => signal.#abort(reason);
+  }
+
+  constructor(key) {
+    if (key !== ABORT_SIGNAL_KEY) {
+      throw new TypeError("Illegal constructor");
+    }
+
+    super();
+  }
+
+  static abort(reason) {
+    const signal = createAbortSignal();
+    signal.#abort(reason);
+
+    return signal;
+  }
+
+  // The timer does not keep the event loop running
+  static timeout(milliseconds) {
+    milliseconds = Number(milliseconds);
+
+    if (!Number.isFinite(milliseconds) || milliseconds < 0) {
+      throw new TypeError(`Invalid timeout: ${milliseconds}`);
+    }
+
+    const signal = createAbortSignal();
+
+    __setUnrefTimeout(() => {
+      signal.#abort(new DOMException("The operation timed out.", "TimeoutError"));
+    }, milliseconds);
+
+    return signal;
+  }
+
+  // Aborted as soon as one of `signals` is
+  static any(signals) {
+    const signal = createAbortSignal();
+
+    for (const source of signals) {
+      if (!(source instanceof AbortSignal)) {
+        throw new TypeError("AbortSignal.any() expects AbortSignals");
+      }
+
+      if (source.#aborted) {
+        signal.#abort(source.#reason);
+        return signal;
+      }
+    }
+
+    // Dependent signals are not sources themselves, their sources are
+    for (const source of signals) {
+      const sources = source.#sources.length > 0 ? source.#sources : [source];
+
+      for (const root of sources) {
+        if (!signal.#sources.includes(root)) {
+          signal.#sources.push(root);
+          root.#addDependent(signal);
+        }
+      }
+    }
+
+    return signal;
+  }
+
+  get onabort() {
+    return this.#onabort;
+  }
+
+  set onabort(handler) {
+    this.#onabort = handler;
+    this.#updateRetention();
+  }
+
+  addEventListener(type, listener, options) {
+    super.addEventListener(type, listener, options);
+    this.#updateRetention();
+  }
+
+  removeEventListener(type, listener) {
+    super.removeEventListener(type, listener);
+    this.#updateRetention();
+  }
+
+  get aborted() {
+    return this.#aborted;
+  }
+
+  get reason() {
+    return this.#reason;
+  }
+
+  throwIfAborted() {
+    if (this.#aborted) {
+      throw this.#reason;
+    }
+  }
+
+  #addDependent(signal) {
+    // Collected dependents are pruned as the set grows
+    if (this.#dependents.size >= this.#pruneAt) {
+      for (const ref of this.#dependents) {
+        if (ref.deref() === undefined) {
+          this.#dependents.delete(ref);
+        }
+      }
+
+      this.#pruneAt = Math.max(16, this.#dependents.size * 2);
+    }
+
+    this.#dependents.add(new WeakRef(signal));
+  }
+
+  // A dependent that can still be aborted is kept alive by its sources
+  // while someone listens to it
+  #updateRetention() {
+    const listened =
+      !this.#aborted &&
+      (this.#onabort !== null || listenerCount(this, "abort") > 0);
+
+    for (const source of this.#sources) {
+      if (listened) {
+        source.#listened.add(this);
+      } else {
+        source.#listened.delete(this);
+      }
+    }
+  }
+
+  #abort(reason) {
+    if (this.#aborted) {
+      return;
+    }
+
+    this.#aborted = true;
+    this.#reason =
+      reason === undefined
+        ? new DOMException("This operation was aborted", "AbortError")
+        : reason;
+
+    const event = new Event("abort");
+
+    try {
+      this.onabort?.call(this, event);
+    } catch (err) {
+      console.error(err?.stack ?? err);
+    }
+
+    this.dispatchEvent(event);
+
+    const dependents = [...this.#dependents].map((ref) => ref.deref());
+    this.#dependents.clear();
+    this.#listened.clear();
+
+    for (const dependent of dependents) {
+      dependent?.#abort(this.#reason);
+    }
+
+    this.#updateRetention();
+  }
+
+  get [Symbol.toStringTag]() {
+    return "AbortSignal";
+  }
+}
+
+class AbortController {
+  #signal = createAbortSignal();
+
+  get signal() {
+    return this.#signal;
+  }
+
+  abort(reason) {
+    signalAbort(this.#signal, reason);
+  }
+
+  get [Symbol.toStringTag]() {
+    return "AbortController";
+  }
+}
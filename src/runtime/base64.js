@@ -0,0 +1,37 @@
+// atob() and btoa() of the HTML spec, `__atob` and `__btoa` are provided by
+// the host and return null on invalid input
+
+function atob(data) {
+  if (arguments.length === 0) {
+    throw new TypeError("1 argument required, but only 0 present.");
+  }
+
+  // Web IDL DOMString conversion, throws for symbols
+  const decoded = __atob(`${data}`);
+
+  if (decoded === null) {
+    throw new DOMException(
+      "The string to be decoded is not correctly encoded.",
+      "InvalidCharacterError"
+    );
+  }
+
+  return decoded;
+}
+
+function btoa(data) {
+  if (arguments.length === 0) {
+    throw new TypeError("1 argument required, but only 0 present.");
+  }
+
+  const encoded = __btoa(`${data}`);
+
+  if (encoded === null) {
+    throw new DOMException(
+      "The string to be encoded contains characters outside of the Latin1 range.",
+      "InvalidCharacterError"
+    );
+  }
+
+  return encoded;
+}
@@ -0,0 +1,135 @@
+// UTF-8 encoding, lone surrogates are replaced by U+FFFD
+function utf8Encode(string) {
+  return __encode(String(string));
+}
+
+// UTF-8 decoding, a leading BOM is skipped and invalid sequences are
+// replaced by U+FFFD
+function utf8Decode(bytes) {
+  return new TextDecoder().decode(bytes);
+}
+
+// Copy of the bytes of a BufferSource
+function bufferSourceBytes(source) {
+  if (source instanceof ArrayBuffer) {
+    return new Uint8Array(source.slice(0));
+  }
+
+  if (ArrayBuffer.isView(source)) {
+    return new Uint8Array(
+      source.buffer.slice(source.byteOffset, source.byteOffset + source.byteLength)
+    );
+  }
+
+  return null;
+}
+
+function concatBytes(chunks) {
+  const length = chunks.reduce((length, chunk) => length + chunk.byteLength, 0);
+  const bytes = new Uint8Array(length);
+
+  let offset = 0;
+
+  for (const chunk of chunks) {
+    bytes.set(chunk, offset);
+    offset += chunk.byteLength;
+  }
+
+  return bytes;
+}
+
+// Set from the Blob class body, bytes of a Blob without copying them
+let blobBytes;
+
+class Blob {
+  #bytes;
+  #type;
+
+  static {
+    blobBytes = (blob) => blob.#bytes;
+  }
+
+  constructor(parts = [], options = {}) {
+    const chunks = [];
+
+    for (const part of parts) {
+      if (part instanceof Blob) {
+        chunks.push(part.#bytes);
+      } else {
+        chunks.push(bufferSourceBytes(part) ?? utf8Encode(part));
+      }
+    }
+
+    const type = String(options.type ?? "");
+
+    this.#bytes = concatBytes(chunks);
+    this.#type = /^[\x20-\x7e]*$/.test(type) ? type.toLowerCase() : "";
+  }
+
+  get size() {
+    return this.#bytes.byteLength;
+  }
+
+  get type() {
+    return this.#type;
+  }
+
+  slice(start = 0, end = this.size, type = "") {
+    const relative = (index) =>
+      index < 0 ? Math.max(this.size + index, 0) : Math.min(index, this.size);
+
+    return new Blob([this.#bytes.subarray(relative(start), relative(end))], {
+      type,
+    });
+  }
+
+  async arrayBuffer() {
+    return this.#bytes.slice().buffer;
+  }
+
+  async text() {
+    return utf8Decode(this.#bytes);
+  }
+
+  stream() {
+    const bytes = this.#bytes.slice();
+
+    return new ReadableStream({
+      start(controller) {
+        if (bytes.byteLength > 0) {
+          controller.enqueue(bytes);
+        }
+
+        controller.close();
+      },
+    });
+  }
+
+  get [Symbol.toStringTag]() {
+    return "Blob";
+  }
+}
+
+class File extends Blob {
+  #name;
+  #lastModified;
+
+  constructor(bits, name, options = {}) {
+    super(bits, options);
+
+    this.#name = String(name);
+    this.#lastModified = Number(options.lastModified ?? Date.now());
+  }
+
+  get name() {
+    return this.#name;
+  }
+
+  get lastModified() {
+    return this.#lastModified;
+  }
+
+  get [Symbol.toStringTag]() {
+    return "File";
+  }
+}
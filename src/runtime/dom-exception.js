@@ -0,0 +1,48 @@
+// Legacy codes of the error names that have one, per WebIDL
+const DOM_EXCEPTION_CODES = {
+  IndexSizeError: 1,
+  HierarchyRequestError: 3,
+  WrongDocumentError: 4,
+  InvalidCharacterError: 5,
+  NoModificationAllowedError: 7,
+  NotFoundError: 8,
+  NotSupportedError: 9,
+  InvalidStateError: 11,
+  SyntaxError: 12,
+  InvalidModificationError: 13,
+  NamespaceError: 14,
+  InvalidAccessError: 15,
+  TypeMismatchError: 17,
+  SecurityError: 18,
+  NetworkError: 19,
+  AbortError: 20,
+  URLMismatchError: 21,
+  QuotaExceededError: 22,
+  TimeoutError: 23,
+  InvalidNodeTypeError: 24,
+  DataCloneError: 25,
+};
+
+class DOMException extends Error {
+  #name;
+  #code;
+
+  constructor(message = "", name = "Error") {
+    super(String(message));
+
+    this.#name = String(name);
+    this.#code = DOM_EXCEPTION_CODES[this.#name] ?? 0;
+  }
+
+  get name() {
+    return this.#name;
+  }
+
+  get code() {
+    return this.#code;
+  }
+
+  get [Symbol.toStringTag]() {
+    return "DOMException";
+  }
+}
@@ -0,0 +1,111 @@
+// Encoders and decoders are implemented by the host: `__encode`,
+// `__encodeInto`, `__encodingForLabel` and `__decode`
+
+class TextEncoder {
+  get encoding() {
+    return "utf-8";
+  }
+
+  encode(input = "") {
+    return __encode(String(input));
+  }
+
+  encodeInto(source, destination) {
+    return __encodeInto(String(source), destination);
+  }
+
+  get [Symbol.toStringTag]() {
+    return "TextEncoder";
+  }
+}
+
+class TextDecoder {
+  #encoding;
+  #fatal;
+  #ignoreBOM;
+  // Incomplete sequence at the end of the previous chunk, while streaming
+  #pending = null;
+  #bomSeen = false;
+
+  constructor(label = "utf-8", options = {}) {
+    const encoding = __encodingForLabel(String(label));
+
+    if (encoding === undefined) {
+      throw new RangeError(`The encoding label provided ('${label}') is invalid.`);
+    }
+
+    this.#encoding = encoding;
+    this.#fatal = Boolean(options.fatal);
+    this.#ignoreBOM = Boolean(options.ignoreBOM);
+  }
+
+  get encoding() {
+    return this.#encoding;
+  }
+
+  get fatal() {
+    return this.#fatal;
+  }
+
+  get ignoreBOM() {
+    return this.#ignoreBOM;
+  }
+
+  decode(input, options = {}) {
+    const stream = Boolean(options.stream);
+
+    let bytes;
+
+    if (input === undefined) {
+      bytes = new Uint8Array(0);
+    } else if (input instanceof ArrayBuffer) {
+      bytes = new Uint8Array(input);
+    } else if (ArrayBuffer.isView(input)) {
+      bytes = new Uint8Array(input.buffer, input.byteOffset, input.byteLength);
+    } else {
+      throw new TypeError("The input must be an ArrayBuffer or a view");
+    }
+
+    if (this.#pending !== null) {
+      const joined = new Uint8Array(this.#pending.byteLength + bytes.byteLength);
+      joined.set(this.#pending);
+      joined.set(bytes, this.#pending.byteLength);
+
+      bytes = joined;
+      this.#pending = null;
+    }
+
+    let text;
+    let pending;
+
+    try {
+      [text, pending] = __decode(bytes, this.#encoding, this.#fatal, stream);
+    } catch (err) {
+      this.#bomSeen = false;
+      throw err;
+    }
+
+    if (pending > 0) {
+      this.#pending = bytes.slice(bytes.byteLength - pending);
+    }
+
+    // Only the first character of a stream can be a byte order mark
+    if (!this.#ignoreBOM && !this.#bomSeen && text.length > 0) {
+      this.#bomSeen = true;
+
+      if (text.charCodeAt(0) === 0xfeff) {
+        text = text.slice(1);
+      }
+    }
+
+    if (!stream) {
+      this.#bomSeen = false;
+    }
+
+    return text;
+  }
+
+  get [Symbol.toStringTag]() {
+    return "TextDecoder";
+  }
+}
@@ -1,6 +1,115 @@
 class Event {
-  constructor(type) {
+  #defaultPrevented = false;
+
+  constructor(type, init = {}) {
     this.type = type;
+    this.cancelable = init.cancelable ?? false;
+  }
+
+  get defaultPrevented() {
+    return this.#defaultPrevented;
+  }
+
+  preventDefault() {
+    if (this.cancelable) {
+      this.#defaultPrevented = true;
+    }
+  }
+}
+
+class ErrorEvent extends Event {
+  constructor(type, init = {}) {
+    super(type, init);
+    this.message = init.message ?? "";
+    this.filename = init.filename ?? "";
+    this.lineno = init.lineno ?? 0;
+    this.colno = init.colno ?? 0;
+    this.error = init.error;
+  }
+}
+
+class PromiseRejectionEvent extends Event {
+  constructor(type, init = {}) {
+    super(type, init);
+    this.promise = init.promise;
+    this.reason = init.reason;
+  }
+}
+
+// Set from the EventTarget class body, number of listeners of `type`
+let listenerCount;
+
+// Listeners of a single object, e.g. an AbortSignal. Listeners throwing do
+// not stop the dispatch, their errors are logged
+class EventTarget {
+  // Event type to { listener, once } entries, in registration order
+  #listeners = new Map();
+
+  static {
+    listenerCount = (target, type) => target.#listeners.get(type)?.length ?? 0;
+  }
+
+  addEventListener(type, listener, options = {}) {
+    if (listener === null || listener === undefined) {
+      return;
+    }
+
+    const once = typeof options === "object" && Boolean(options?.once);
+    const signal = typeof options === "object" ? options?.signal : undefined;
+
+    if (signal?.aborted) {
+      return;
+    }
+
+    const entries = this.#listeners.get(type) ?? [];
+
+    if (entries.some((entry) => entry.listener === listener)) {
+      return;
+    }
+
+    entries.push({ listener, once });
+    this.#listeners.set(type, entries);
+
+    signal?.addEventListener(
+      "abort",
+      () => this.removeEventListener(type, listener),
+      { once: true }
+    );
+  }
+
+  removeEventListener(type, listener) {
+    const entries = this.#listeners.get(type) ?? [];
+
+    this.#listeners.set(
+      type,
+      entries.filter((entry) => entry.listener !== listener)
+    );
+  }
+
+  // Returns false if a listener canceled the event
+  dispatchEvent(event) {
+    for (const entry of this.#listeners.get(event.type) ?? []) {
+      // Removed by a previous listener
+      if (!this.#listeners.get(event.type).includes(entry)) {
+        continue;
+      }
+
+      if (entry.once) {
+        this.removeEventListener(event.type, entry.listener);
+      }
+
+      try {
+        if (typeof entry.listener === "function") {
+          entry.listener.call(this, event);
+        } else {
+          entry.listener.handleEvent(event);
+        }
+      } catch (err) {
+        console.error(err?.stack ?? err);
+      }
+    }
+
+    return !event.defaultPrevented;
   }
 }
 
@@ -19,6 +128,7 @@ function removeEventListener(eventType, eventTarget) {
   eventMap.delete(eventType);
 }
 
+// Returns false if a listener canceled the event
 function dispatchEvent(event) {
   const eventType = event.type;
   const eventHandlers = eventMap.get(eventType);
@@ -27,4 +137,6 @@ function dispatchEvent(event) {
       eventHandler.call(this, event);
     }
   }
+
+  return !event.defaultPrevented;
 }
@@ -0,0 +1,194 @@
+// Normalize a body init to null, a string, bytes, a Blob or a ReadableStream,
+// along with the content type it implies
+function extractBody(body) {
+  if (body === undefined || body === null) {
+    return [null, null];
+  }
+
+  if (body instanceof ReadableStream) {
+    return [body, null];
+  }
+
+  if (body instanceof Blob) {
+    return [body, body.type || null];
+  }
+
+  if (body instanceof FormData) {
+    const boundary = multipartBoundary();
+
+    return [
+      serializeFormData(body, boundary),
+      `multipart/form-data; boundary=${boundary}`,
+    ];
+  }
+
+  if (body instanceof URLSearchParams) {
+    return [
+      body.toString(),
+      "application/x-www-form-urlencoded;charset=UTF-8",
+    ];
+  }
+
+  const bytes = bufferSourceBytes(body);
+
+  if (bytes !== null) {
+    return [bytes, null];
+  }
+
+  return [String(body), "text/plain;charset=UTF-8"];
+}
+
+// Set from the class body, `bodySource` gives the host the body of a Request
+// or Response without consuming it, `takeBodySource` marks it used and
+// `cloneBodySource` gives the body of a clone
+let bodySource;
+let takeBodySource;
+let cloneBodySource;
+
+// Body readers shared by Request and Response
+class Body {
+  #source;
+  #stream = null;
+  #bodyUsed = false;
+
+  static {
+    bodySource = (body) => body.#stream ?? body.#source;
+    takeBodySource = (body) => body.#takeSource();
+    cloneBodySource = (body) => body.#cloneSource();
+  }
+
+  constructor(source) {
+    this.#source = source;
+  }
+
+  get body() {
+    if (this.#source === null || this.#source instanceof ReadableStream) {
+      return this.#source;
+    }
+
+    if (this.#stream === null) {
+      const source = this.#source;
+
+      this.#stream = new ReadableStream({
+        async start(controller) {
+          const bytes =
+            source instanceof Blob
+              ? new Uint8Array(await source.arrayBuffer())
+              : typeof source === "string"
+              ? utf8Encode(source)
+              : source.slice();
+
+          if (bytes.byteLength > 0) {
+            controller.enqueue(bytes);
+          }
+
+          controller.close();
+        },
+      });
+    }
+
+    return this.#stream;
+  }
+
+  get bodyUsed() {
+    return this.#bodyUsed;
+  }
+
+  // Read the whole body as bytes, a body can only be read once
+  async #consume() {
+    if (this.#bodyUsed) {
+      throw new TypeError("Body has already been used");
+    }
+
+    const stream = this.#stream ?? this.#source;
+
+    if (stream instanceof ReadableStream && stream.locked) {
+      throw new TypeError("Body is locked");
+    }
+
+    this.#bodyUsed = true;
+
+    const source = this.#source;
+
+    if (source === null) {
+      return new Uint8Array(0);
+    }
+
+    if (typeof source === "string") {
+      return utf8Encode(source);
+    }
+
+    if (source instanceof Uint8Array) {
+      return source.slice();
+    }
+
+    if (source instanceof Blob) {
+      return new Uint8Array(await source.arrayBuffer());
+    }
+
+    const chunks = [];
+
+    for await (const chunk of source) {
+      const bytes =
+        typeof chunk === "string" ? utf8Encode(chunk) : bufferSourceBytes(chunk);
+
+      if (bytes === null) {
+        throw new TypeError("Body chunks must be strings or buffers");
+      }
+
+      chunks.push(bytes);
+    }
+
+    return concatBytes(chunks);
+  }
+
+  async arrayBuffer() {
+    return (await this.#consume()).buffer;
+  }
+
+  async text() {
+    return utf8Decode(await this.#consume());
+  }
+
+  async json() {
+    return JSON.parse(await this.text());
+  }
+
+  async blob() {
+    const type = this.headers.get("content-type") ?? "";
+
+    return new Blob([await this.#consume()], { type });
+  }
+
+  async formData() {
+    const type = this.headers.get("content-type") ?? "";
+
+    return parseFormData(await this.#consume(), type);
+  }
+
+  #takeSource() {
+    if (this.#bodyUsed || this.#stream?.locked || this.#source?.locked) {
+      throw new TypeError("Body has already been used");
+    }
+
+    this.#bodyUsed = true;
+
+    return this.#source;
+  }
+
+  // Source for a clone, a stream is teed so that both bodies can be read
+  #cloneSource() {
+    if (this.#bodyUsed || this.#stream?.locked || this.#source?.locked) {
+      throw new TypeError("Body has already been used");
+    }
+
+    if (this.#source instanceof ReadableStream) {
+      const [source, clone] = this.#source.tee();
+      this.#source = source;
+
+      return clone;
+    }
+
+    return this.#source;
+  }
+}
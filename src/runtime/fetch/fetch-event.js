@@ -1,9 +1,41 @@
+// Set from the FetchEvent class body, ends the dispatch of an event and
+// returns whether a listener called respondWith
+let endFetchDispatch;
+
+// Set from the FetchEvent class body
+let passesThroughOnException;
+
+// Set from the FetchEvent class body, resolves once the dispatch ended, the
+// response was sent and the waitUntil promises settled
+let fetchEventSettled;
+
 class FetchEvent extends Event {
   #request;
   #requestId;
   #startTime;
   #respondWith;
   #responded;
+  #dispatched = false;
+  #passThrough = false;
+  // Promises extending the lifetime of the event
+  #pending = 0;
+  #onSettled = null;
+
+  static {
+    endFetchDispatch = (event) => {
+      event.#dispatched = true;
+      return event.#responded;
+    };
+    passesThroughOnException = (event) => event.#passThrough;
+    fetchEventSettled = (event) =>
+      new Promise((resolve) => {
+        if (event.#pending === 0) {
+          resolve();
+        } else {
+          event.#onSettled = resolve;
+        }
+      });
+  }
 
   get request() {
     return this.#request;
@@ -31,8 +63,50 @@ class FetchEvent extends Event {
       throw new TypeError("Already responded to this FetchEvent.");
     }
 
+    // The host is told that nobody responded as soon as listeners return
+    if (this.#dispatched) {
+      throw new TypeError(
+        "respondWith() must be called synchronously from a fetch listener."
+      );
+    }
+
     this.#responded = true;
-    this.#respondWith(response).catch((err) => console.warn(err));
+    this.#extendLifetime(
+      this.#respondWith(response).catch((err) => console.warn(err))
+    );
+  }
+
+  // The host keeps running the event loop after the response is sent, until
+  // `promise` settles or for at most the grace period
+  waitUntil(promise) {
+    if (this.#dispatched && this.#pending === 0) {
+      throw new DOMException(
+        "waitUntil() must be called while the event is active.",
+        "InvalidStateError"
+      );
+    }
+
+    this.#extendLifetime(
+      Promise.resolve(promise).catch((err) => {
+        console.error("waitUntil promise rejected:", err?.stack ?? err);
+      })
+    );
+  }
+
+  #extendLifetime(promise) {
+    this.#pending++;
+
+    promise.finally(() => {
+      if (--this.#pending === 0 && this.#dispatched) {
+        this.#onSettled?.();
+      }
+    });
+  }
+
+  // Uncaught exceptions forward the request to the origin instead of
+  // failing it, when the host has one configured
+  passThroughOnException() {
+    this.#passThrough = true;
   }
 
   [Symbol.toStringTag]() {
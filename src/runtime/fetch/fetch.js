@@ -0,0 +1,76 @@
+// Outbound requests, `__fetch` is provided by the host and gives the id of
+// the op and a promise resolved with a plain response object once the event
+// loop gets it
+async function fetch(input, init = {}) {
+  const request = new Request(input, init);
+  const signal = request.signal;
+
+  signal.throwIfAborted();
+
+  const body =
+    bodySource(request) === null ? null : await request.arrayBuffer();
+
+  signal.throwIfAborted();
+
+  const [opId, pending] = __fetch({
+    url: request.url,
+    method: request.method,
+    headers: [...request.headers.entries()],
+    body,
+    redirect: request.redirect,
+    // Non-standard, in milliseconds
+    timeout: init.timeout,
+  });
+
+  // Aborting drops the request on the host side
+  const response = await new Promise((resolve, reject) => {
+    const onAbort = () => {
+      __cancelOp(opId);
+      reject(signal.reason);
+    };
+
+    signal.addEventListener("abort", onAbort, { once: true });
+
+    pending.then(
+      (response) => {
+        signal.removeEventListener("abort", onAbort);
+        resolve(response);
+      },
+      (err) => {
+        signal.removeEventListener("abort", onAbort);
+        reject(err);
+      }
+    );
+  });
+
+  // Manual redirects are opaque, the worker only learns that one happened
+  if (
+    request.redirect === "manual" &&
+    REDIRECT_STATUSES.includes(response.status)
+  ) {
+    const res = new Response(null);
+
+    initResponse(res, { status: 0, type: "opaqueredirect", url: response.url });
+    setHeadersGuard(res.headers, "immutable");
+
+    return res;
+  }
+
+  const nullBody = NULL_BODY_STATUSES.includes(response.status);
+
+  const res = new Response(nullBody ? null : response.body, {
+    status: response.status,
+    statusText: response.statusText,
+    headers: response.headers,
+  });
+
+  setHeadersGuard(res.headers, "immutable");
+
+  initResponse(res, {
+    type: "basic",
+    url: response.url,
+    redirected: response.redirected,
+  });
+
+  return res;
+}
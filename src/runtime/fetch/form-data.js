@@ -0,0 +1,176 @@
+class FormData {
+  // [name, value] pairs, values are strings or Files
+  #entries = [];
+
+  constructor(form) {
+    if (form !== undefined) {
+      throw new TypeError("FormData cannot be built from a form element");
+    }
+  }
+
+  #entry(name, value, filename) {
+    name = String(name);
+
+    if (!(value instanceof Blob)) {
+      if (filename !== undefined) {
+        throw new TypeError("A filename can only be given with a Blob value");
+      }
+
+      return [name, String(value)];
+    }
+
+    if (!(value instanceof File) || filename !== undefined) {
+      value = new File([value], filename ?? value.name ?? "blob", {
+        type: value.type,
+        lastModified: value.lastModified,
+      });
+    }
+
+    return [name, value];
+  }
+
+  append(name, value, filename) {
+    this.#entries.push(this.#entry(name, value, filename));
+  }
+
+  set(name, value, filename) {
+    const entry = this.#entry(name, value, filename);
+    const index = this.#entries.findIndex(([n]) => n === entry[0]);
+
+    if (index === -1) {
+      this.#entries.push(entry);
+    } else {
+      this.#entries[index] = entry;
+      this.#entries = this.#entries.filter(([n], i) => n !== entry[0] || i <= index);
+    }
+  }
+
+  delete(name) {
+    name = String(name);
+
+    this.#entries = this.#entries.filter(([n]) => n !== name);
+  }
+
+  get(name) {
+    name = String(name);
+
+    return this.#entries.find(([n]) => n === name)?.[1] ?? null;
+  }
+
+  getAll(name) {
+    name = String(name);
+
+    return this.#entries.filter(([n]) => n === name).map(([, v]) => v);
+  }
+
+  has(name) {
+    name = String(name);
+
+    return this.#entries.some(([n]) => n === name);
+  }
+
+  *entries() {
+    for (const [name, value] of this.#entries) {
+      yield [name, value];
+    }
+  }
+
+  *keys() {
+    for (const [name] of this.#entries) {
+      yield name;
+    }
+  }
+
+  *values() {
+    for (const [, value] of this.#entries) {
+      yield value;
+    }
+  }
+
+  forEach(callback, thisArg) {
+    for (const [name, value] of this.#entries) {
+      callback.call(thisArg, value, name, this);
+    }
+  }
+
+  [Symbol.iterator]() {
+    return this.entries();
+  }
+
+  get [Symbol.toStringTag]() {
+    return "FormData";
+  }
+}
+
+function multipartBoundary() {
+  let boundary = "----formdata-";
+
+  for (let i = 0; i < 24; i++) {
+    boundary += Math.floor(Math.random() * 16).toString(16);
+  }
+
+  return boundary;
+}
+
+// multipart/form-data encoding of `form`
+function serializeFormData(form, boundary) {
+  const escape = (value) =>
+    value.replace(/\n/g, "%0A").replace(/\r/g, "%0D").replace(/"/g, "%22");
+  const normalizeNewlines = (value) => value.replace(/\r?\n|\r/g, "\r\n");
+
+  const chunks = [];
+
+  for (const [name, value] of form) {
+    let head = `--${boundary}\r\nContent-Disposition: form-data; name="${escape(
+      normalizeNewlines(name)
+    )}"`;
+
+    if (typeof value === "string") {
+      chunks.push(utf8Encode(`${head}\r\n\r\n${normalizeNewlines(value)}\r\n`));
+    } else {
+      head += `; filename="${escape(value.name)}"\r\n`;
+      head += `Content-Type: ${value.type || "application/octet-stream"}\r\n\r\n`;
+
+      chunks.push(utf8Encode(head), blobBytes(value), utf8Encode("\r\n"));
+    }
+  }
+
+  chunks.push(utf8Encode(`--${boundary}--\r\n`));
+
+  return concatBytes(chunks);
+}
+
+// Form data of a body, multipart bodies are parsed by the host
+function parseFormData(bytes, contentType) {
+  const essence = contentType.split(";")[0].trim().toLowerCase();
+  const form = new FormData();
+
+  switch (essence) {
+    case "multipart/form-data": {
+      const boundary = /;\s*boundary=(?:"([^"]+)"|([^;\s]+))/i.exec(contentType);
+
+      if (boundary === null) {
+        throw new TypeError("Missing multipart boundary");
+      }
+
+      for (const part of __parseMultipart(bytes, boundary[1] ?? boundary[2])) {
+        if (part.filename === undefined) {
+          form.append(part.name, part.value);
+        } else {
+          const file = new File([part.data], part.filename, { type: part.type });
+          form.append(part.name, file);
+        }
+      }
+
+      return form;
+    }
+    case "application/x-www-form-urlencoded":
+      for (const [name, value] of new URLSearchParams(utf8Decode(bytes))) {
+        form.append(name, value);
+      }
+
+      return form;
+    default:
+      throw new TypeError(`Cannot parse a "${contentType}" body as form data`);
+  }
+}
@@ -1,25 +1,163 @@
-class Headers extends Map {
+const HEADER_NAME = /^[!#$%&'*+\-.^_`|~0-9A-Za-z]+$/;
+const HEADER_VALUE_INVALID = /[\0\r\n]/;
+const HTTP_WHITESPACE = /^[\t\n\r ]+|[\t\n\r ]+$/g;
+
+// Set from the class body, changes the guard of a Headers object. "none"
+// allows every change, "immutable" none at all (e.g. the headers of an
+// incoming request or of a fetched response). Unlike browsers there are no
+// "request" or "response" guards: workers may set forbidden names such as
+// Cookie, Host or Set-Cookie
+let setHeadersGuard;
+
+class Headers {
+  // Lowercased name -> values, in insertion order
+  #list = new Map();
+  #guard = "none";
+
+  static {
+    setHeadersGuard = (headers, guard) => {
+      headers.#guard = guard;
+    };
+  }
+
   constructor(init) {
-    super();
-    if (init) {
-      if (Array.isArray(init)) {
-        for (let [name, value] of init) {
-          this.append(name, value);
-        }
-      } else {
-        for (let name in init) {
-          this.append(name, init[name]);
+    if (init === undefined || init === null) {
+      return;
+    }
+
+    if (typeof init !== "object") {
+      throw new TypeError("Failed to construct 'Headers': invalid init");
+    }
+
+    if (typeof init[Symbol.iterator] === "function") {
+      for (const pair of init) {
+        const entry = [...pair];
+
+        if (entry.length !== 2) {
+          throw new TypeError(
+            "Failed to construct 'Headers': header entries must be pairs"
+          );
         }
+
+        this.append(entry[0], entry[1]);
+      }
+    } else {
+      for (const name of Object.keys(init)) {
+        this.append(name, init[name]);
       }
     }
   }
 
+  #normalizeName(name) {
+    name = String(name);
+
+    if (!HEADER_NAME.test(name)) {
+      throw new TypeError(`Invalid header name: "${name}"`);
+    }
+
+    return name.toLowerCase();
+  }
+
+  #normalizeValue(name, value) {
+    value = String(value).replace(HTTP_WHITESPACE, "");
+
+    if (HEADER_VALUE_INVALID.test(value)) {
+      throw new TypeError(`Invalid header value for "${name}"`);
+    }
+
+    return value;
+  }
+
+  #checkGuard() {
+    if (this.#guard === "immutable") {
+      throw new TypeError("Headers are immutable");
+    }
+  }
+
   append(name, value) {
-    let header = this.get(name);
-    if (header) {
-      this.set(name, header + ", " + value);
+    name = this.#normalizeName(name);
+    value = this.#normalizeValue(name, value);
+    this.#checkGuard();
+
+    const values = this.#list.get(name);
+
+    if (values) {
+      values.push(value);
     } else {
-      this.set(name, value);
+      this.#list.set(name, [value]);
+    }
+  }
+
+  set(name, value) {
+    name = this.#normalizeName(name);
+    value = this.#normalizeValue(name, value);
+    this.#checkGuard();
+
+    this.#list.set(name, [value]);
+  }
+
+  delete(name) {
+    name = this.#normalizeName(name);
+    this.#checkGuard();
+
+    this.#list.delete(name);
+  }
+
+  get(name) {
+    name = this.#normalizeName(name);
+
+    return this.#list.get(name)?.join(", ") ?? null;
+  }
+
+  has(name) {
+    name = this.#normalizeName(name);
+
+    return this.#list.has(name);
+  }
+
+  // Set-Cookie values cannot be combined
+  getSetCookie() {
+    return [...(this.#list.get("set-cookie") ?? [])];
+  }
+
+  // Sorted by name, each Set-Cookie value is a separate entry
+  *entries() {
+    const names = [...this.#list.keys()].sort();
+
+    for (const name of names) {
+      if (name === "set-cookie") {
+        for (const value of this.#list.get(name)) {
+          yield [name, value];
+        }
+      } else {
+        yield [name, this.#list.get(name).join(", ")];
+      }
+    }
+  }
+
+  *keys() {
+    for (const [name] of this.entries()) {
+      yield name;
+    }
+  }
+
+  *values() {
+    for (const [, value] of this.entries()) {
+      yield value;
+    }
+  }
+
+  forEach(callback, thisArg) {
+    for (const [name, value] of this.entries()) {
+      callback.call(thisArg, value, name, this);
     }
   }
+
+  [Symbol.iterator]() {
+    return this.entries();
+  }
+
+  get [Symbol.toStringTag]() {
+    return "Headers";
+  }
 }
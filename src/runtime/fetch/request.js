@@ -1,6 +1,106 @@
-class Request {
-  constructor(url, options) {
-    this.url = url;
-    this.options = options;
+const NORMALIZED_METHODS = ["DELETE", "GET", "HEAD", "OPTIONS", "POST", "PUT"];
+const FORBIDDEN_METHODS = ["CONNECT", "TRACE", "TRACK"];
+const REDIRECT_MODES = ["follow", "error", "manual"];
+
+class Request extends Body {
+  #url;
+  #method;
+  #headers;
+  #redirect;
+  #signal;
+
+  constructor(input, init = {}) {
+    const request = input instanceof Request ? input : null;
+
+    let method = String(init.method ?? request?.method ?? "GET");
+
+    if (!/^[!#$%&'*+\-.^_`|~0-9A-Za-z]+$/.test(method)) {
+      throw new TypeError(`Invalid method: "${method}"`);
+    }
+
+    if (FORBIDDEN_METHODS.includes(method.toUpperCase())) {
+      throw new TypeError(`Forbidden method: "${method}"`);
+    }
+
+    if (NORMALIZED_METHODS.includes(method.toUpperCase())) {
+      method = method.toUpperCase();
+    }
+
+    // The body of `input` is taken unless a new one is given
+    let source = null;
+    let type = null;
+
+    if (init.body !== undefined) {
+      [source, type] = extractBody(init.body);
+    } else if (request !== null && request.body !== null) {
+      source = takeBodySource(request);
+    }
+
+    if (source !== null && (method === "GET" || method === "HEAD")) {
+      throw new TypeError("Request with GET/HEAD method cannot have body");
+    }
+
+    const redirect = init.redirect ?? request?.redirect ?? "follow";
+
+    if (!REDIRECT_MODES.includes(redirect)) {
+      throw new TypeError(`Invalid redirect mode: "${redirect}"`);
+    }
+
+    super(source);
+
+    // Relative URLs are kept as is, there is no base to resolve them against
+    this.#url =
+      request?.url ??
+      (URL.canParse(String(input)) ? new URL(input).href : String(input));
+    this.#method = method;
+    this.#headers = new Headers(init.headers ?? request?.headers);
+    this.#redirect = redirect;
+
+    // Follows the given signal, aborting it aborts the request
+    const signal = init.signal !== undefined ? init.signal : request?.signal;
+
+    if (signal !== undefined && signal !== null && !(signal instanceof AbortSignal)) {
+      throw new TypeError("signal must be an AbortSignal");
+    }
+
+    this.#signal = signal ? AbortSignal.any([signal]) : createAbortSignal();
+
+    if (type !== null && !this.#headers.has("content-type")) {
+      this.#headers.set("content-type", type);
+    }
+  }
+
+  get url() {
+    return this.#url;
+  }
+
+  get method() {
+    return this.#method;
+  }
+
+  get headers() {
+    return this.#headers;
+  }
+
+  get redirect() {
+    return this.#redirect;
+  }
+
+  get signal() {
+    return this.#signal;
+  }
+
+  clone() {
+    return new Request(this.#url, {
+      method: this.#method,
+      headers: this.#headers,
+      body: cloneBodySource(this),
+      redirect: this.#redirect,
+      signal: this.#signal,
+    });
+  }
+
+  get [Symbol.toStringTag]() {
+    return "Request";
   }
 }
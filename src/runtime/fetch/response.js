@@ -1,9 +1,145 @@
-class Response {
+const NULL_BODY_STATUSES = [101, 103, 204, 205, 304];
+const REDIRECT_STATUSES = [301, 302, 303, 307, 308];
+
+// Set from the class body, sets the properties a Response only gets from the
+// runtime: its type, the URL it was fetched from and whether it was redirected
+let initResponse;
+
+class Response extends Body {
+  #status;
+  #statusText;
+  #headers;
+  #type = "default";
+  #url = "";
+  #redirected = false;
+
+  static {
+    initResponse = (response, { status, type, url, redirected }) => {
+      response.#status = status ?? response.#status;
+      response.#type = type ?? response.#type;
+      response.#url = url ?? response.#url;
+      response.#redirected = redirected ?? response.#redirected;
+    };
+  }
+
   constructor(body = null, init = {}) {
-    this.body = body;
-    this.headers = new Headers(init.headers ?? {});
-    this.status = init.status ?? 200;
-    this.statusText = init.statusText;
+    const status = init.status ?? 200;
+    const statusText = String(init.statusText ?? "");
+
+    if (!Number.isInteger(status) || status < 200 || status > 599) {
+      throw new RangeError(`Invalid response status: ${status}`);
+    }
+
+    // reason-phrase = *( HTAB / SP / VCHAR / obs-text )
+    if (!/^[\t\x20-\x7e\x80-\xff]*$/.test(statusText)) {
+      throw new TypeError(`Invalid response status text: "${statusText}"`);
+    }
+
+    const [source, type] = extractBody(body);
+
+    if (source !== null && NULL_BODY_STATUSES.includes(status)) {
+      throw new TypeError(`Response with status ${status} cannot have body`);
+    }
+
+    super(source);
+
+    this.#status = status;
+    this.#statusText = statusText;
+    this.#headers = new Headers(init.headers);
+
+    if (type !== null && !this.#headers.has("content-type")) {
+      this.#headers.set("content-type", type);
+    }
+  }
+
+  // Network error, rejected when sent by a worker
+  static error() {
+    const response = new Response(null);
+
+    initResponse(response, { status: 0, type: "error" });
+    setHeadersGuard(response.headers, "immutable");
+
+    return response;
+  }
+
+  static redirect(url, status = 302) {
+    if (!REDIRECT_STATUSES.includes(status)) {
+      throw new RangeError(`Invalid redirect status: ${status}`);
+    }
+
+    const response = new Response(null, {
+      status,
+      headers: { location: new URL(url).href },
+    });
+
+    setHeadersGuard(response.headers, "immutable");
+
+    return response;
+  }
+
+  static json(data, init = {}) {
+    const body = JSON.stringify(data);
+
+    if (body === undefined) {
+      throw new TypeError("Data is not JSON serializable");
+    }
+
+    const headers = new Headers(init.headers);
+
+    if (!headers.has("content-type")) {
+      headers.set("content-type", "application/json");
+    }
+
+    return new Response(body, { ...init, headers });
+  }
+
+  get status() {
+    return this.#status;
+  }
+
+  get statusText() {
+    return this.#statusText;
+  }
+
+  get ok() {
+    return this.#status >= 200 && this.#status <= 299;
+  }
+
+  get headers() {
+    return this.#headers;
   }
 
+  get type() {
+    return this.#type;
+  }
+
+  get url() {
+    return this.#url;
+  }
+
+  get redirected() {
+    return this.#redirected;
+  }
+
+  clone() {
+    const clone = new Response(cloneBodySource(this), {
+      // Network errors and opaque redirects have status 0, restored below
+      status: this.#status === 0 ? 200 : this.#status,
+      statusText: this.#statusText,
+      headers: this.#headers,
+    });
+
+    initResponse(clone, {
+      status: this.#status,
+      type: this.#type,
+      url: this.#url,
+      redirected: this.#redirected,
+    });
+
+    return clone;
+  }
+
+  get [Symbol.toStringTag]() {
+    return "Response";
+  }
 }
@@ -1,20 +1,86 @@
+// Header pairs sent to the host, iterating Headers keeps each Set-Cookie
+// value separate
+function serializeHeaders(headers) {
+  return headers ? [...new Headers(headers)] : [];
+}
+
+// Controller of the signal of the request being handled
+let requestController = null;
+
+// Abort the signal of a request whose client is gone
+function abortRequest(controller) {
+  controller?.abort(new DOMException("The client disconnected.", "AbortError"));
+}
+
+// Write the chunks of a body stream to the host, one at a time so that a
+// slow client slows down the stream
+async function pumpStream(stream, streamId, message, controller) {
+  const reader = stream.getReader();
+
+  try {
+    while (true) {
+      const { done, value } = await reader.read();
+
+      if (done) {
+        break;
+      }
+
+      // Rejects when the client is gone
+      await message.writeStream(streamId, value).catch((err) => {
+        abortRequest(controller);
+        throw err;
+      });
+    }
+
+    message.closeStream(streamId);
+  } catch (err) {
+    message.closeStream(streamId, String(err?.message ?? err));
+    reader.cancel(err).catch(() => {});
+  }
+}
+
 onMessage((message) => {
   console.log("Got controller message", JSON.stringify(message));
 
   switch (message.kind) {
     // Runtime fetch message
     case "fetch":
-      const request = new Request(
-        message.request.url,
-        message.request.options ?? {}
-      );
+      const controller = new AbortController();
+      requestController = controller;
+
+      const request = new Request(message.request.url, {
+        method: message.request.method,
+        headers: message.request.headers,
+        // Bodies of GET and HEAD requests are ignored
+        body: ["GET", "HEAD"].includes(message.request.method)
+          ? null
+          : message.request.body,
+        signal: controller.signal,
+      });
+
+      setHeadersGuard(request.headers, "immutable");
 
       console.log("Got request", request);
-      dispatchEvent(
-        new FetchEvent(request, async (response) => {
-          console.log("Got response", response);
 
-          const res = await Promise.resolve(response).catch((err) => {
+      const event = new FetchEvent(request, async (response) => {
+        console.log("Got response", response);
+
+        let failed = false;
+
+        const res = await Promise.resolve(response)
+          .then((res) => {
+            // Opaque redirects have no status or location to send back
+            const invalid = ["error", "opaqueredirect"].includes(res?.type);
+
+            if (!(res instanceof Response) || invalid) {
+              throw new TypeError("respondWith() expects a valid Response");
+            }
+
+            return res;
+          })
+          .catch((err) => {
+            failed = true;
+
             // User did not handled error
             postMessage({
               type: "error",
@@ -24,19 +90,56 @@ onMessage((message) => {
             return new Response(err.stack, { status: 500 });
           });
 
-          message.sendResponse({
-            body: res.body, // await res.arrayBuffer(),
-            headers: Object.fromEntries(res.headers?.entries()),
-            status: res.status,
-            statusText: res.statusText,
-          });
-        })
-      );
+        if (failed && passesThroughOnException(event)) {
+          message.sendNoResponse("pass-through");
+          return;
+        }
 
-      break;
-      case "timer":
-        __wakeUp();
+        let body = bodySource(res);
+
+        if (body instanceof Blob) {
+          body = await body.arrayBuffer();
+        }
+
+        const stream = body instanceof ReadableStream ? body : null;
+
+        const streamId = message.sendResponse({
+          body: stream ? null : body,
+          stream: stream !== null,
+          headers: serializeHeaders(res.headers),
+          status: res.status,
+          statusText: res.statusText,
+        });
+
+        if (stream) {
+          await pumpStream(stream, streamId, message, controller);
+        }
+      });
+
+      try {
+        dispatchEvent(event);
+      } catch (err) {
+        // Fails the event, unless it passes through
+        if (!passesThroughOnException(event) || endFetchDispatch(event)) {
+          throw err;
+        }
+
+        console.error("Passing through after exception:", err?.stack ?? err);
+        message.sendNoResponse("pass-through");
+        fetchEventSettled(event).then(() => message.sendSettled());
         break;
+      }
+
+      if (!endFetchDispatch(event)) {
+        message.sendNoResponse("not-handled");
+      }
+
+      fetchEventSettled(event).then(() => message.sendSettled());
+      break;
+    // The client of the request being handled disconnected
+    case "abort":
+      abortRequest(requestController);
+      break;
     default:
       console.warn(`Unknown message kind: "${message.kind}"`);
   }
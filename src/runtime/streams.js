@@ -0,0 +1,333 @@
+class ReadableStreamDefaultController {
+  #stream;
+
+  // Not constructible by user code, `stream` exposes the stream internals
+  constructor(stream) {
+    this.#stream = stream;
+  }
+
+  get desiredSize() {
+    return this.#stream.desiredSize();
+  }
+
+  enqueue(chunk) {
+    this.#stream.enqueue(chunk);
+  }
+
+  close() {
+    this.#stream.close();
+  }
+
+  error(reason) {
+    this.#stream.error(reason);
+  }
+}
+
+class ReadableStreamDefaultReader {
+  #stream;
+
+  // Not constructible by user code, use `stream.getReader()`
+  constructor(stream) {
+    this.#stream = stream;
+  }
+
+  read() {
+    if (!this.#stream) {
+      return Promise.reject(new TypeError("Reader was released"));
+    }
+
+    return this.#stream.read();
+  }
+
+  cancel(reason) {
+    if (!this.#stream) {
+      return Promise.reject(new TypeError("Reader was released"));
+    }
+
+    return this.#stream.cancel(reason);
+  }
+
+  releaseLock() {
+    if (this.#stream) {
+      this.#stream.release();
+      this.#stream = null;
+    }
+  }
+}
+
+class ReadableStream {
+  // readable, closed or errored
+  #state = "readable";
+  #storedError;
+  #queue = [];
+  #queueSize = 0;
+  #pendingReads = [];
+  #closeRequested = false;
+  #started = false;
+  #pulling = false;
+  #pullAgain = false;
+  #locked = false;
+  #source;
+  #highWaterMark;
+  #size;
+  #controller;
+
+  constructor(source = {}, strategy = {}) {
+    this.#source = source;
+    this.#highWaterMark = strategy.highWaterMark ?? 1;
+    this.#size = strategy.size ?? (() => 1);
+    this.#controller = new ReadableStreamDefaultController({
+      desiredSize: () => this.#desiredSize(),
+      enqueue: (chunk) => this.#enqueue(chunk),
+      close: () => this.#close(),
+      error: (reason) => this.#error(reason),
+    });
+
+    const start = () => source.start?.(this.#controller);
+
+    Promise.resolve()
+      .then(start)
+      .then(
+        () => {
+          this.#started = true;
+          this.#pullIfNeeded();
+        },
+        (reason) => this.#error(reason)
+      );
+  }
+
+  get locked() {
+    return this.#locked;
+  }
+
+  getReader() {
+    if (this.#locked) {
+      throw new TypeError("ReadableStream is locked");
+    }
+
+    this.#locked = true;
+
+    return new ReadableStreamDefaultReader({
+      read: () => this.#read(),
+      cancel: (reason) => this.#cancel(reason),
+      release: () => (this.#locked = false),
+    });
+  }
+
+  cancel(reason) {
+    if (this.#locked) {
+      return Promise.reject(new TypeError("ReadableStream is locked"));
+    }
+
+    return this.#cancel(reason);
+  }
+
+  // Two branches receiving the same chunks, the stream is canceled once both
+  // branches are
+  tee() {
+    const reader = this.getReader();
+    const controllers = [];
+    const canceled = [false, false];
+    let reading = null;
+
+    const readOnce = async () => {
+      try {
+        const { done, value } = await reader.read();
+
+        controllers.forEach((controller, index) => {
+          if (canceled[index]) {
+            return;
+          }
+
+          if (done) {
+            controller.close();
+          } else {
+            controller.enqueue(value);
+          }
+        });
+      } catch (err) {
+        controllers.forEach((controller) => controller.error(err));
+      }
+    };
+
+    const pull = () => (reading ??= readOnce().finally(() => (reading = null)));
+
+    const branch = (index) =>
+      new ReadableStream({
+        start(controller) {
+          controllers[index] = controller;
+        },
+        pull,
+        cancel(reason) {
+          canceled[index] = true;
+
+          if (canceled[0] && canceled[1]) {
+            return reader.cancel(reason);
+          }
+        },
+      });
+
+    return [branch(0), branch(1)];
+  }
+
+  async *[Symbol.asyncIterator]() {
+    const reader = this.getReader();
+
+    try {
+      while (true) {
+        const { done, value } = await reader.read();
+
+        if (done) {
+          return;
+        }
+
+        yield value;
+      }
+    } finally {
+      reader.releaseLock();
+    }
+  }
+
+  #desiredSize() {
+    switch (this.#state) {
+      case "errored":
+        return null;
+      case "closed":
+        return 0;
+      default:
+        return this.#highWaterMark - this.#queueSize;
+    }
+  }
+
+  #enqueue(chunk) {
+    if (this.#closeRequested || this.#state !== "readable") {
+      throw new TypeError("ReadableStream is closed");
+    }
+
+    const read = this.#pendingReads.shift();
+
+    if (read) {
+      read.resolve({ value: chunk, done: false });
+    } else {
+      const size = this.#size(chunk);
+      this.#queue.push({ chunk, size });
+      this.#queueSize += size;
+    }
+
+    this.#pullIfNeeded();
+  }
+
+  #close() {
+    if (this.#closeRequested || this.#state !== "readable") {
+      throw new TypeError("ReadableStream is closed");
+    }
+
+    this.#closeRequested = true;
+
+    if (this.#queue.length === 0) {
+      this.#finishClose();
+    }
+  }
+
+  #finishClose() {
+    this.#state = "closed";
+
+    for (const read of this.#pendingReads.splice(0)) {
+      read.resolve({ value: undefined, done: true });
+    }
+  }
+
+  #error(reason) {
+    if (this.#state !== "readable") {
+      return;
+    }
+
+    this.#state = "errored";
+    this.#storedError = reason;
+    this.#queue = [];
+    this.#queueSize = 0;
+
+    for (const read of this.#pendingReads.splice(0)) {
+      read.reject(reason);
+    }
+  }
+
+  #read() {
+    if (this.#queue.length > 0) {
+      const { chunk, size } = this.#queue.shift();
+      this.#queueSize -= size;
+
+      if (this.#closeRequested && this.#queue.length === 0) {
+        this.#finishClose();
+      } else {
+        this.#pullIfNeeded();
+      }
+
+      return Promise.resolve({ value: chunk, done: false });
+    }
+
+    switch (this.#state) {
+      case "closed":
+        return Promise.resolve({ value: undefined, done: true });
+      case "errored":
+        return Promise.reject(this.#storedError);
+    }
+
+    return new Promise((resolve, reject) => {
+      this.#pendingReads.push({ resolve, reject });
+      this.#pullIfNeeded();
+    });
+  }
+
+  #cancel(reason) {
+    switch (this.#state) {
+      case "closed":
+        return Promise.resolve();
+      case "errored":
+        return Promise.reject(this.#storedError);
+    }
+
+    this.#queue = [];
+    this.#queueSize = 0;
+    this.#finishClose();
+
+    return Promise.resolve()
+      .then(() => this.#source.cancel?.(reason))
+      .then(() => undefined);
+  }
+
+  #pullIfNeeded() {
+    if (!this.#started || this.#closeRequested || this.#state !== "readable") {
+      return;
+    }
+
+    if (!this.#source.pull) {
+      return;
+    }
+
+    if (this.#pendingReads.length === 0 && this.#desiredSize() <= 0) {
+      return;
+    }
+
+    if (this.#pulling) {
+      this.#pullAgain = true;
+      return;
+    }
+
+    this.#pulling = true;
+
+    Promise.resolve()
+      .then(() => this.#source.pull(this.#controller))
+      .then(
+        () => {
+          this.#pulling = false;
+
+          if (this.#pullAgain) {
+            this.#pullAgain = false;
+            this.#pullIfNeeded();
+          }
+        },
+        (reason) => this.#error(reason)
+      );
+  }
+}
@@ -0,0 +1,300 @@
+// Parsing and serialization are done by the host, `__url*` functions are
+// provided on instantiation
+
+// Set from the class bodies, links a URL and its search params so that
+// changes to one are reflected in the other
+let setSearchParamsUrl;
+let updateSearchParams;
+
+class URLSearchParams {
+  #list = [];
+  #url = null;
+
+  static {
+    setSearchParamsUrl = (params, url) => {
+      params.#url = url;
+    };
+
+    updateSearchParams = (params, search) => {
+      params.#list = __urlSearchParamsParse(search.replace(/^\?/, ""));
+    };
+  }
+
+  constructor(init = "") {
+    if (init instanceof URLSearchParams) {
+      this.#list = init.#list.map(([name, value]) => [name, value]);
+    } else if (typeof init === "object" && init !== null) {
+      if (typeof init[Symbol.iterator] === "function") {
+        for (const pair of init) {
+          const entry = [...pair];
+
+          if (entry.length !== 2) {
+            throw new TypeError(
+              "Failed to construct 'URLSearchParams': entries must be pairs"
+            );
+          }
+
+          this.#list.push([String(entry[0]), String(entry[1])]);
+        }
+      } else {
+        for (const name of Object.keys(init)) {
+          this.#list.push([name, String(init[name])]);
+        }
+      }
+    } else {
+      this.#list = __urlSearchParamsParse(String(init).replace(/^\?/, ""));
+    }
+  }
+
+  // Reflect changes in the associated URL
+  #update() {
+    if (this.#url !== null) {
+      const query = this.toString();
+      setUrlSearch(this.#url, query === "" ? "" : "?" + query);
+    }
+  }
+
+  get size() {
+    return this.#list.length;
+  }
+
+  append(name, value) {
+    this.#list.push([String(name), String(value)]);
+    this.#update();
+  }
+
+  delete(name, value) {
+    name = String(name);
+
+    this.#list = this.#list.filter(
+      ([n, v]) => n !== name || (value !== undefined && v !== String(value))
+    );
+    this.#update();
+  }
+
+  get(name) {
+    name = String(name);
+
+    return this.#list.find(([n]) => n === name)?.[1] ?? null;
+  }
+
+  getAll(name) {
+    name = String(name);
+
+    return this.#list.filter(([n]) => n === name).map(([, v]) => v);
+  }
+
+  has(name, value) {
+    name = String(name);
+
+    return this.#list.some(
+      ([n, v]) => n === name && (value === undefined || v === String(value))
+    );
+  }
+
+  set(name, value) {
+    name = String(name);
+    value = String(value);
+
+    const index = this.#list.findIndex(([n]) => n === name);
+
+    if (index === -1) {
+      this.#list.push([name, value]);
+    } else {
+      this.#list[index][1] = value;
+      this.#list = this.#list.filter(([n], i) => n !== name || i <= index);
+    }
+
+    this.#update();
+  }
+
+  // Stable sort by name, compared by UTF-16 code units
+  sort() {
+    this.#list = this.#list
+      .map((entry, index) => [entry, index])
+      .sort(([a, i], [b, j]) => (a[0] < b[0] ? -1 : a[0] > b[0] ? 1 : i - j))
+      .map(([entry]) => entry);
+    this.#update();
+  }
+
+  *entries() {
+    for (const [name, value] of this.#list) {
+      yield [name, value];
+    }
+  }
+
+  *keys() {
+    for (const [name] of this.#list) {
+      yield name;
+    }
+  }
+
+  *values() {
+    for (const [, value] of this.#list) {
+      yield value;
+    }
+  }
+
+  forEach(callback, thisArg) {
+    for (const [name, value] of this.#list) {
+      callback.call(thisArg, value, name, this);
+    }
+  }
+
+  [Symbol.iterator]() {
+    return this.entries();
+  }
+
+  toString() {
+    return __urlSearchParamsStringify(this.#list);
+  }
+
+  get [Symbol.toStringTag]() {
+    return "URLSearchParams";
+  }
+}
+
+// Set from the URL class body, sets the search without updating the params
+let setUrlSearch;
+
+class URL {
+  #components;
+  #searchParams;
+
+  static {
+    setUrlSearch = (url, search) => {
+      url.#components = __urlSet(url.#components.href, "search", search);
+    };
+  }
+
+  constructor(url, base) {
+    const components =
+      base === undefined
+        ? __urlParse(String(url))
+        : __urlParse(String(url), String(base));
+
+    if (components === null) {
+      throw new TypeError(`Invalid URL: "${url}"`);
+    }
+
+    this.#components = components;
+    this.#searchParams = new URLSearchParams(components.search);
+    setSearchParamsUrl(this.#searchParams, this);
+  }
+
+  static canParse(url, base) {
+    return (
+      (base === undefined
+        ? __urlParse(String(url))
+        : __urlParse(String(url), String(base))) !== null
+    );
+  }
+
+  #set(name, value) {
+    this.#components = __urlSet(this.#components.href, name, String(value));
+
+    if (name === "href" || name === "search") {
+      updateSearchParams(this.#searchParams, this.#components.search);
+    }
+  }
+
+  get href() {
+    return this.#components.href;
+  }
+
+  set href(value) {
+    this.#set("href", value);
+  }
+
+  get origin() {
+    return this.#components.origin;
+  }
+
+  get protocol() {
+    return this.#components.protocol;
+  }
+
+  set protocol(value) {
+    this.#set("protocol", value);
+  }
+
+  get username() {
+    return this.#components.username;
+  }
+
+  set username(value) {
+    this.#set("username", value);
+  }
+
+  get password() {
+    return this.#components.password;
+  }
+
+  set password(value) {
+    this.#set("password", value);
+  }
+
+  get host() {
+    return this.#components.host;
+  }
+
+  set host(value) {
+    this.#set("host", value);
+  }
+
+  get hostname() {
+    return this.#components.hostname;
+  }
+
+  set hostname(value) {
+    this.#set("hostname", value);
+  }
+
+  get port() {
+    return this.#components.port;
+  }
+
+  set port(value) {
+    this.#set("port", value);
+  }
+
+  get pathname() {
+    return this.#components.pathname;
+  }
+
+  set pathname(value) {
+    this.#set("pathname", value);
+  }
+
+  get search() {
+    return this.#components.search;
+  }
+
+  set search(value) {
+    this.#set("search", value);
+  }
+
+  get searchParams() {
+    return this.#searchParams;
+  }
+
+  get hash() {
+    return this.#components.hash;
+  }
+
+  set hash(value) {
+    this.#set("hash", value);
+  }
+
+  toString() {
+    return this.href;
+  }
+
+  toJSON() {
+    return this.href;
+  }
+
+  get [Symbol.toStringTag]() {
+    return "URL";
+  }
+}
@@ -7,31 +7,56 @@ fn _initialize_v8() {
     v8::V8::initialize();
 }
 
+static V8_INIT: Once = Once::new();
+
 pub fn initialize_v8() {
-    static V8_INIT: Once = Once::new();
-    V8_INIT.call_once(_initialize_v8);
+    initialize_v8_with_flags(&[]);
 }
 
+/// Flags are process-wide and can only be set before V8 is initialized
+pub fn initialize_v8_with_flags(flags: &[String]) {
+    let mut initialized = false;
+
+    V8_INIT.call_once(|| {
+        if !flags.is_empty() {
+            v8::V8::set_flags_from_string(&flags.join(" "));
+        }
+
+        _initialize_v8();
+        initialized = true;
+    });
+
+    if !initialized && !flags.is_empty() {
+        println!(
+            "V8 already initialized, ignoring flags: {}",
+            flags.join(" ")
+        );
+    }
+}
+
+/// Id set on the origin of classic scripts, the same for all of them
+const SCRIPT_ID: i32 = 123;
+
 pub fn load_script<'s>(
     scope: &mut v8::HandleScope<'s>,
     name: &str,
-    source: &str
-) -> v8::Local<'s, v8::Script> {
+    source: &str,
+) -> Option<v8::Local<'s, v8::Script>> {
     let name = v8::String::new(scope, name).unwrap();
-    let source = v8::String::new(scope, source).unwrap();
+    let source = v8::String::new(scope, source)?;
     let source_map_url = v8::String::empty(scope);
     let origin = v8::ScriptOrigin::new(
-      scope,
-      name.into(),
-      0,
-      0,
-      false,
-      123,
-      source_map_url.into(),
-      true,
-      false,
-      false,
+        scope,
+        name.into(),
+        0,
+        0,
+        false,
+        SCRIPT_ID,
+        source_map_url.into(),
+        false,
+        false,
+        false,
     );
 
-    v8::Script::compile(scope, source, Some(&origin)).unwrap()
+    v8::Script::compile(scope, source, Some(&origin))
 }
@@ -1,7 +1,22 @@
 use std::fmt::Write;
 use v8::{HandleScope, Local};
 
+/// Nested objects deeper than this are not expanded
+const MAX_DEPTH: usize = 4;
+
 pub fn inspect_v8_value(value: Local<v8::Value>, scope: &mut HandleScope) -> String {
+    // Getters may throw, the exception must not escape to the caller
+    let scope = &mut v8::TryCatch::new(scope);
+
+    inspect(value, scope, &mut Vec::new())
+}
+
+/// `seen` holds the objects being inspected, from the outermost one
+fn inspect(
+    value: Local<v8::Value>,
+    scope: &mut HandleScope,
+    seen: &mut Vec<Local<v8::Object>>,
+) -> String {
     let mut output = String::new();
 
     if value.is_string() {
@@ -38,11 +53,28 @@ pub fn inspect_v8_value(value: Local<v8::Value>, scope: &mut HandleScope) -> Str
     }
 
     let object = value.to_object(scope).unwrap();
-    let keys = object
-        .get_own_property_names(scope, v8::GetPropertyNamesArgs::default())
-        .unwrap();
 
-    let mut output = String::new();
+    if seen
+        .iter()
+        .any(|parent| parent.strict_equals(object.into()))
+    {
+        return String::from("[Circular]");
+    }
+
+    if seen.len() >= MAX_DEPTH {
+        return String::from(if value.is_array() {
+            "[Array]"
+        } else {
+            "[Object]"
+        });
+    }
+
+    let keys = match object.get_own_property_names(scope, v8::GetPropertyNamesArgs::default()) {
+        Some(keys) => keys,
+        None => return String::from("[Object]"),
+    };
+
+    seen.push(object);
 
     write!(&mut output, "{}", if value.is_array() { "[" } else { "{" })
         .expect("Error writing to output string");
@@ -53,8 +85,10 @@ pub fn inspect_v8_value(value: Local<v8::Value>, scope: &mut HandleScope) -> Str
             .get_index(scope, i)
             .unwrap()
             .to_rust_string_lossy(scope);
-        let val = object.get(scope, key).unwrap();
-        let val_str = inspect_v8_value(val, scope);
+        let val_str = match object.get(scope, key) {
+            Some(val) => inspect(val, scope, seen),
+            None => String::from("[Exception]"),
+        };
 
         if value.is_array() {
             write!(&mut output, "{}{}", if i > 0 { ", " } else { "" }, val_str)
@@ -74,5 +108,7 @@ pub fn inspect_v8_value(value: Local<v8::Value>, scope: &mut HandleScope) -> Str
     write!(&mut output, "{}", if value.is_array() { "]" } else { "}" })
         .expect("Error writing to output string");
 
+    seen.pop();
+
     output
 }